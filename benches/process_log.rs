@@ -0,0 +1,26 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use clap::Parser;
+use criterion::{criterion_group, criterion_main, Criterion};
+use tournament_review::{process_log, Args};
+
+/// Hand-dealt East game with a riichi ron dealt in by a pushing player after a pon, an open tsumo after a chi, a
+/// riichi facing a dama ankan hand until the exhaustive draw, and a dama ron with a red five on the carried stick
+/// and honba. Every own discard goes through the danger calculation, many of them against a riichi
+const SAMPLE_LOG: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/benches/data/sample_log.json");
+
+fn bench_process_log(c: &mut Criterion) {
+    let no_aliases = BTreeMap::new();
+    let mut group = c.benchmark_group("process_log");
+    for (label, extra_args) in [("danger_on", &[][..]), ("danger_off", &["--skip-danger"][..])] {
+        let args = Args::parse_from(["tournament_review", "--file", SAMPLE_LOG].iter().chain(extra_args).copied());
+        group.bench_function(label, |b| {
+            b.iter(|| process_log(Path::new(SAMPLE_LOG), &args, &no_aliases).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_process_log);
+criterion_main!(benches);
@@ -0,0 +1,86 @@
+use std::path::{Path, PathBuf};
+
+/// Errors from libriichi and serde are kept as their source without a shared type
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+pub type Result<T, E = YilError> = std::result::Result<T, E>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum YilError {
+    #[error("failed to access {path:?}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("failed to write to stdout")]
+    Stdout(#[source] std::io::Error),
+    /// The file is not a tenhou log this tool understands
+    #[error("invalid tenhou log")]
+    TenhouParse(#[source] BoxError),
+    #[error("failed to convert the log to mjai events")]
+    MjaiConvert(#[source] BoxError),
+    /// libriichi rejected an event for the state of one seat
+    #[error("failed to update the state of seat {seat}")]
+    StateUpdate {
+        seat: usize,
+        #[source]
+        source: BoxError,
+    },
+    /// The log converted fine but contradicts itself, such as Hora deltas not adding up
+    #[error("{0}")]
+    InconsistentLog(String),
+    #[error("invalid config {path:?}")]
+    Config {
+        path: PathBuf,
+        #[source]
+        source: toml::de::Error,
+    },
+    #[error("invalid yaku aliases {path:?}")]
+    YakuAliases {
+        path: PathBuf,
+        #[source]
+        source: toml::de::Error,
+    },
+    #[error("failed to download {url}")]
+    Download {
+        url: String,
+        #[source]
+        source: BoxError,
+    },
+    #[error("{path:?} has {count} kyoku, there is no kyoku {index}")]
+    NoKyoku { path: PathBuf, index: usize, count: usize },
+    #[error("invalid info csv {path:?}: {message}")]
+    InfoCsv { path: PathBuf, message: String },
+    #[error(transparent)]
+    Csv(#[from] csv::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error("failed to process {path:?}")]
+    Process {
+        path: PathBuf,
+        #[source]
+        source: Box<YilError>,
+    },
+}
+
+impl YilError {
+    /// For `map_err` on io results of `path`
+    pub fn io(path: &Path) -> impl FnOnce(std::io::Error) -> YilError + '_ {
+        move |source| YilError::Io {
+            path: path.to_owned(),
+            source,
+        }
+    }
+
+    /// The message followed by every source, like anyhow's `{:#}`
+    pub fn chain(&self) -> String {
+        let mut message = self.to_string();
+        let mut source = std::error::Error::source(self);
+        while let Some(err) = source {
+            message += &format!(": {err}");
+            source = err.source();
+        }
+        message
+    }
+}
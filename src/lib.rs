@@ -0,0 +1,3186 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::UNIX_EPOCH;
+
+use clap::{CommandFactory, FromArgMatches, Parser};
+use csv::{QuoteStyle, Writer, WriterBuilder};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use riichi::algo::shanten::calc_all;
+use riichi::convlog::tenhou::{EndStatus, Log};
+use riichi::mjai::Event;
+use riichi::must_tile;
+use riichi::state::PlayerState;
+use riichi::tile::Tile;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+
+mod error;
+#[cfg(feature = "net")]
+mod net;
+
+pub use error::{BoxError, Result, YilError};
+
+/// Summary lines, sent to stderr instead while stdout carries the ndjson rows. Progress and warnings go through
+/// `tracing` and always end up on stderr.
+macro_rules! status {
+    ($args:expr, $($arg:tt)*) => {
+        if $args.writes_ndjson() {
+            eprintln!($($arg)*);
+        } else {
+            println!($($arg)*);
+        }
+    };
+}
+
+macro_rules! csv_struct {
+    ($(#[$meta:meta])* $vis:vis struct $name:ident {
+        $( $(#[$field_meta:meta])* $field:ident : $ty:ty ),* $(,)?
+    }) => {
+        $(#[$meta])*
+        #[derive(Debug, Default, Clone, Serialize, Deserialize)]
+        // columns missing from older files read back as zero
+        #[serde(default)]
+        $vis struct $name {
+            $( $(#[$field_meta])* pub $field: $ty, )*
+        }
+
+        impl std::ops::AddAssign<&$name> for $name {
+            fn add_assign(&mut self, rhs: &$name) {
+                $( self.$field += rhs.$field; )*
+            }
+        }
+
+        impl $name {
+            pub const CSV_HEADERS: &'static [&'static str] = &[$(stringify!($field)),*];
+
+            pub fn to_csv_values(&self) -> Vec<String> {
+                vec![$(self.$field.to_string()),*]
+            }
+        }
+    };
+}
+
+csv_struct! {
+    pub struct PlayerInfo {
+        /// Count of StartKyoku events
+        kyoku_count: u32,
+        /// Count of Hora events from self
+        agari_count: u32,
+        /// Count of Hora events targeting self
+        dealin_count: u32,
+        /// Count of ReachAccepted events
+        riichi_count: u32,
+        /// Count of Hora events from self where self declared riichi
+        riichi_agari_count: u32,
+        /// Count of Hora events from self where menzen and not self declared riichi
+        dama_agari_count: u32,
+        /// Count of Hora events from self where not menzen
+        open_agari_count: u32,
+        /// Count of EndKyoku events where not menzen
+        open_count: u32,
+        /// Sum of self shanten at StartKyoku
+        total_haipai_shanten: u32,
+        /// Sum of score deltas for Hora events from self
+        total_agari_score: u32,
+        /// Sum of score deltas for Hora events targeting self
+        total_dealin_score: u32,
+        /// Sum of uradora in tehai for Hora events from self where self declared riichi
+        ura_count: u32,
+        /// Count of Hora events from self where agari is yakuman
+        yakuman_count: u32,
+        /// Count of Hora events from self where agari is sanbaiman or more
+        sanbaiman_count: u32,
+        /// Count of Hora events from self where agari is baiman or more
+        baiman_count: u32,
+        /// Count of EndKyoku events where self is tenpai for any yakuman, includes won yakuman
+        yakuman_chance: u32,
+        /// Count of Hora events targetting self where someone else wins with ippatsu and we were not in riichi
+        ippatsu_dealin_count: u32,
+        /// Count of Dahai events from self where agari has ippatsu chance and we discarded a non-genbutsu tile while we were not in riichi
+        ippatsu_brazen_count: u32,
+        /// Sum of how many tiles are being waited on when riichi is called
+        total_riichi_wait: u32,
+        /// Sum of how many tiles were being waited on when self agari
+        total_agari_waits: u32,
+        /// Count of Hora events targetting self where actor is closed and not riichi
+        dama_dealin_count: u32,
+        /// Count of Hora events targetting self where actor is closed and not riichi and point delta is mangan or more
+        dama_mangan_dealin_count: u32,
+        /// Count of actions taken but not necessarily recorded (if state.can_act() is true)
+        action_count: u32,
+        /// Total time spent in a game
+        seconds_played: u32,
+        /// Count of ReachAccepted events from self where a different tenpai discard from the same hand left more live tiles
+        locked_bad_wait_count: u32,
+        /// Count of Dora events, the kan dora indicators revealed after the starting one, see avg_dora_indicators
+        total_dora_indicators_seen: u32,
+        /// Count of Hora events targetting self where the actor's last discard before winning was tedashi
+        tedashi_then_dealin_count: u32,
+        /// Count of Hora events where self pays for someone else's yakuman through pao without being the ron target
+        pao_liability_count: u32,
+        /// Count of Hora events from self where the yakuman payment is moved onto a liable player through pao
+        pao_benefit_count: u32,
+        /// Count of Hora events from self without any dora, red five or ura dora han
+        no_dora_agari_count: u32,
+        /// Sum of dora, red five and ura dora han for Hora events from self
+        total_dora_in_wins: u32,
+        /// Count of Hora events from self where self reached tenpai after every opponent that declared riichi this kyoku,
+        /// opponents only count as tenpai once their riichi is accepted since their hands are hidden
+        slow_tenpai_win_count: u32,
+        /// Count of Hora events targetting self on the final discard after the live wall ran out
+        houtei_dealin_count: u32,
+        /// Count of Chi events from self, always called from kamicha
+        chi_from_kamicha: u32,
+        /// Count of Pon events from self called from shimocha
+        pon_from_shimocha: u32,
+        /// Count of Pon events from self called from toimen
+        pon_from_toimen: u32,
+        /// Count of Pon events from self called from kamicha
+        pon_from_kamicha: u32,
+        /// Count of ReachAccepted events from self after an opponent's riichi was already accepted this kyoku
+        chase_riichi_count: u32,
+        /// Count of chase riichi kyoku won by self
+        chase_riichi_agari_count: u32,
+        /// Count of chase riichi kyoku where self dealt in or a riichi that was chased won by tsumo
+        chase_riichi_dealin_count: u32,
+        /// Count of Hora events from self with 1 to 6 own discards, a win before the first discard counts here too
+        agari_turn_1_6: u32,
+        /// Count of Hora events from self with 7 to 9 own discards
+        agari_turn_7_9: u32,
+        /// Count of Hora events from self with 10 to 12 own discards
+        agari_turn_10_12: u32,
+        /// Count of Hora events from self with 13 or more own discards
+        agari_turn_13plus: u32,
+        /// Count of Hora events from self by tsumo with ippatsu
+        ippatsu_tsumo_count: u32,
+        /// Count of Hora events from self by ron with ippatsu
+        ippatsu_ron_count: u32,
+        /// Count of Hora events from self with pinfu
+        pinfu_agari_count: u32,
+        /// Count of Hora events from self with pinfu and riichi or double riichi
+        pinfu_riichi_agari_count: u32,
+        /// Count of Hora events targetting self where the dealt in tile was a tedashi of a tile type kept since before our
+        /// previous two discards, i.e. a tile we held on to and then released anyway
+        held_winning_tile_count: u32,
+        /// Count of games with final scores in the log
+        placed_game_count: u32,
+        /// Sum of final placements (1-4) over those games, ties resolved by --tie-mode
+        total_placement: f64,
+        /// Count of games finished 1st, a shared tie splits the game between the tied ranks
+        rank_1_count: f64,
+        /// Count of games finished 2nd
+        rank_2_count: f64,
+        /// Count of games finished 3rd
+        rank_3_count: f64,
+        /// Count of games finished 4th
+        rank_4_count: f64,
+        /// Count of kyoku where self declared riichi and an opponent won while self did not, by ron on anyone or tsumo
+        riichi_then_loss_count: u32,
+        /// Count of Dahai events from self while facing riichi where the tile has no danger against any riichi opponent
+        genbutsu_dahai_count: u32,
+        /// Count of Dahai events from self
+        total_dahai_count: u32,
+        /// Longest run of consecutive kyoku won by self, within a single game unless --session joins the games
+        max_agari_streak: Max,
+        /// Sum of han for Hora events from self without yakuman yaku, dora included
+        total_han: u32,
+        /// Count of Hora events from self without yakuman yaku
+        han_agari_count: u32,
+        /// Sum of fu for Hora events from self below mangan, limit hands have no fu in the tenhou point string
+        total_fu: u32,
+        /// Count of Hora events from self below mangan
+        fu_agari_count: u32,
+        /// Count of Hora events from self with exactly one yaku besides dora, red five and ura dora
+        single_yaku_agari_count: u32,
+        /// Count of Hora events targetting self while self was tenpai after the dealt in discard
+        tenpai_dealin_count: u32,
+        /// Count of exhaustive draws where self was noten after having been tenpai earlier in the kyoku
+        folded_tenpai_count: u32,
+        /// Count of Hora events from self with sanshoku (Mixed Triple Sequence), open or closed
+        sanshoku_agari_count: u32,
+        /// Count of Hora events from self with ittsuu (Pure Straight), open or closed
+        ittsuu_agari_count: u32,
+        /// Count of Hora events from self by tsumo on the first own draw after self riichi was accepted
+        riichi_declare_turn_tsumo: u32,
+        /// Count of ReachAccepted events from self while 1st in score, ties go to the earlier seat
+        riichi_while_leading_count: u32,
+        /// Count of ReachAccepted events from self while 2nd in score
+        riichi_while_second_count: u32,
+        /// Count of ReachAccepted events from self while 3rd in score
+        riichi_while_third_count: u32,
+        /// Count of ReachAccepted events from self while 4th in score
+        riichi_while_last_count: u32,
+        /// Count of dama agari by ron, dama_agari_count split by win type
+        dama_ron_count: u32,
+        /// Count of dama agari by tsumo
+        dama_tsumo_count: u32,
+        /// Count of Hora events from self by tsumo where calculate_agari finds no yaku for a ron on the same tile, such as
+        /// a closed hand with only menzen tsumo or a shanpon sanankou that ron would break into two concealed triplets
+        tsumo_only_agari_count: u32,
+        /// Count of kyoku where self was tenpai for at least --overfold-min-points when an opponent riichi was accepted,
+        /// then broke tenpai while discarding only genbutsu. Folding from a good one-shanten is not counted since a hand
+        /// that is not tenpai has no ron value to compare against the threshold
+        overfold_count: u32,
+        /// Count of kyoku won by an opponent, by tsumo or ron on anyone, before self made a third discard
+        instant_loss_count: u32,
+        /// Count of Hora events from self without open melds, closed kans do not count since they keep the hand closed
+        agari_by_melds_0: u32,
+        /// Count of Hora events from self with 1 meld
+        agari_by_melds_1: u32,
+        /// Count of Hora events from self with 2 melds
+        agari_by_melds_2: u32,
+        /// Count of Hora events from self with 3 or 4 melds
+        agari_by_melds_3plus: u32,
+        /// With --efficiency, sum of ukeire lost by Dahai events from self against the best discard keeping the same
+        /// shanten, see `discard_ukeire`
+        total_ukeire_loss: u32,
+        /// With --efficiency, count of Dahai events from self counted towards total_ukeire_loss
+        efficiency_dahai_count: u32,
+        /// Sum of squared final placements over the games counted in placed_game_count, for placement_variance
+        total_placement_squared: f64,
+        /// Count of kyoku where an opponent riichi was accepted
+        faced_riichi_count: u32,
+        /// Count of kyoku counted in faced_riichi_count where self neither dealt in nor paid for an opponent tsumo
+        survived_riichi_count: u32,
+        /// Count of Hora events from self worth more than a tenpai with yaku that self left earlier in the kyoku.
+        ///
+        /// After every own discard that leaves the hand tenpai, the best ron value over its waits is kept; when a
+        /// later own discard leaves tenpai, the highest value of that tenpai is remembered. A win counts when its
+        /// score delta without honba and deposits exceeds the highest remembered value. Leaving tenpai to defend
+        /// and winning a bigger hand later counts as well.
+        value_upgrade_agari_count: u32,
+        /// Count of placed games where self was last by score at the StartKyoku of the final kyoku, equal scores
+        /// ranked by seat order
+        last_before_final_count: u32,
+        /// Count of games counted in last_before_final_count that self did not finish last in, a shared 4th place
+        /// counts as last
+        last_place_escapes: u32,
+        /// Count of Hora events targetting self where at least one other player won on the same discard
+        multi_ron_dealin_count: u32,
+        /// Count of Dahai events from self of a non-genbutsu tile while an opponent was in riichi and we were not
+        push_dahai_count: u32,
+        /// Sum over push_dahai_count of the tiles left in hand that were genbutsu against every riichi, the safe
+        /// discards given up by pushing
+        total_safe_tiles_at_push: u32,
+        /// Count of Hora events targetting self with 1 to 6 own discards, the dealt in tile included
+        dealin_turn_1_6: u32,
+        /// Count of Hora events targetting self with 7 to 9 own discards
+        dealin_turn_7_9: u32,
+        /// Count of Hora events targetting self with 10 to 12 own discards
+        dealin_turn_10_12: u32,
+        /// Count of Hora events targetting self with 13 or more own discards
+        dealin_turn_13plus: u32,
+        /// Sum of score deltas from Hora events from self with riichi or double riichi
+        riichi_agari_score: u32,
+        /// riichi_agari_score with the points owed to ura dora taken off. Those are the hand value (the delta without
+        /// honba and deposits) scaled by the share of base points the ura han add, `fu * 2^(han + 2)` up to the limit
+        /// hands. Limit hands have no fu in the log and are scored at 30 fu once the ura han are removed.
+        riichi_agari_score_excl_ura: u32,
+        /// Count of opponents who, after our riichi was accepted, made at least one discard and discarded only
+        /// genbutsu against us for the rest of the kyoku without being in riichi themselves.
+        ///
+        /// Uses the danger calculation of each opponent towards us, so zero with --skip-danger and with --self-only.
+        /// An opponent who was already folding against another riichi, or whose hand only held safe tiles anyway,
+        /// counts the same as one our riichi forced down.
+        riichi_induced_fold_estimate: u32,
+        /// Count of Hora events from self by ron before our next draw after a call, or by tsumo on the first draw
+        /// after it, rinshan draws after a kan included
+        call_then_agari_count: u32,
+        /// Count of Hora events targetting self on an honor tile
+        dealin_honor_count: u32,
+        /// Count of Hora events targetting self on a 1 or 9
+        dealin_terminal_count: u32,
+        /// Count of Hora events targetting self on a 2 through 8
+        dealin_middle_count: u32,
+        /// Count of Hora events from self that brought our score from below zero back to zero or more, riichi deposits
+        /// of the kyoku taken off before the win
+        recovery_agari_count: u32,
+        /// Count of kyoku where self was first tenpai within our first 6 discards, tenpai haipai included
+        early_tenpai_count: u32,
+        /// Count of Dahai events from self of a red five
+        wasted_aka_count: u32,
+        /// Count of Hora events from an opponent in riichi not targetting self, where the winning tile type was in our
+        /// hand at some point after their riichi was accepted and we never discarded it since.
+        ///
+        /// Opponent waits are hidden during the kyoku, so only the winning tile of a kyoku that ended in their win is
+        /// known. Drawn kyoku where we held on to a wait tile cannot be told apart and are not counted.
+        withheld_riichi_winning_tile_count: u32,
+        /// With --efficiency, count of ReachAccepted events from self where another tenpai discard from the same
+        /// 14 tiles would have left fewer live tiles than the wait we riichi on, live tiles being `4 - tiles_seen` of
+        /// every wait. The mirror of locked_bad_wait_count
+        wait_widening_riichi_count: u32,
+        /// Count of Hora events from self by ron where the winning tile is a suited tile of the same suit as and at most
+        /// one number away from a tile of one of our chi or pon. The decomposition of the winning hand is not exposed
+        /// by the agari calculation, so this is the closest proxy for a call that built the wait
+        ron_on_called_shape_count: u32,
+        /// Count of Hora events targetting self where the danger of the dealt in tile towards the winner was zero
+        /// before one of our earlier discards of the kyoku but not before the dealing in one, such as after a new dora
+        /// or a changed wait. Zero with --skip-danger
+        stale_safe_dealin_count: u32,
+        /// Count of kyoku that self did not win and ended open and tenpai with no wait giving a yaku. An open hand
+        /// that was not tenpai at the end cannot be judged and is not counted
+        yakuless_open_count: u32,
+        /// Count of ReachAccepted events from self while leading in a kyoku at or after --late-game-from, ranked like
+        /// riichi_while_leading_count
+        risky_lead_riichi_count: u32,
+        /// Count of Hora events targetting self where another tile left in our hand had zero danger towards the winner
+        /// before the dealing in discard. Zero with --skip-danger
+        avoidable_dealin_count: u32,
+        /// kyoku_count of the kyoku self was dealer in
+        oya_kyoku_count: u32,
+        /// Count of kyoku self was dealer in and won
+        oya_agari_count: u32,
+        /// Count of kyoku self was dealer in and dealt in
+        oya_dealin_count: u32,
+        /// kyoku_count of the kyoku self was not dealer in
+        ko_kyoku_count: u32,
+        /// Count of kyoku self was not dealer in and won
+        ko_agari_count: u32,
+        /// Count of kyoku self was not dealer in and dealt in
+        ko_dealin_count: u32,
+        /// Sum of the self score change of every kyoku, the Hora and Ryukyoku deltas with their collected riichi sticks
+        /// minus the own riichi deposit
+        total_score_delta: i64,
+        /// Sum of the squared per-kyoku deltas of total_score_delta, for their variance
+        total_score_delta_sq: i64,
+        /// Count of StartKyoku events where self started tenpai
+        haipai_shanten_0: u32,
+        /// Count of StartKyoku events where self started one-shanten
+        haipai_shanten_1: u32,
+        /// Count of StartKyoku events where self started two-shanten
+        haipai_shanten_2: u32,
+        /// Count of StartKyoku events where self started three-shanten
+        haipai_shanten_3: u32,
+        /// Count of StartKyoku events where self started four-shanten
+        haipai_shanten_4: u32,
+        /// Count of StartKyoku events where self started five-shanten
+        haipai_shanten_5: u32,
+        /// Count of StartKyoku events where self started six-shanten, the worst a starting hand can be
+        haipai_shanten_6: u32
+    }
+}
+
+/// Counter merged by keeping the larger value, for records such as the longest streak
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Max(pub u32);
+
+impl std::ops::AddAssign for Max {
+    fn add_assign(&mut self, rhs: Max) {
+        self.0 = self.0.max(rhs.0);
+    }
+}
+
+impl std::fmt::Display for Max {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Won kyoku runs of one player over a stretch of kyoku, keeping the runs touching either end so that consecutive
+/// stretches can be joined without replaying them
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+struct AgariStreak {
+    kyoku: u32,
+    leading: u32,
+    trailing: u32,
+    longest: u32,
+}
+
+impl AgariStreak {
+    fn push(&mut self, won: bool) {
+        if won {
+            if self.leading == self.kyoku {
+                self.leading += 1;
+            }
+            self.trailing += 1;
+            self.longest = self.longest.max(self.trailing);
+        } else {
+            self.trailing = 0;
+        }
+        self.kyoku += 1;
+    }
+
+    /// Runs of this stretch directly followed by `next`
+    fn then(&self, next: &AgariStreak) -> AgariStreak {
+        AgariStreak {
+            kyoku: self.kyoku + next.kyoku,
+            leading: if self.leading == self.kyoku {
+                self.kyoku + next.leading
+            } else {
+                self.leading
+            },
+            trailing: if next.trailing == next.kyoku {
+                self.trailing + next.kyoku
+            } else {
+                next.trailing
+            },
+            longest: self.longest.max(next.longest).max(self.trailing + next.leading),
+        }
+    }
+}
+
+/// Draws available in a 4 player kyoku, each kan moves one of them to the dead wall so rinshan draws count towards it
+const LIVE_WALL_DRAWS: u32 = 70;
+
+/// Tenhou yaku names that count dora rather than hand shape
+const DORA_YAKU: &[&str] = &["Dora", "Red Five", "Ura Dora"];
+
+/// Mahjong Soul `mode_id`s of the four player ranked rooms, east and south of bronze, silver, gold, jade and throne
+const RANKED_MODE_IDS: &[u64] = &[2, 3, 5, 6, 8, 9, 11, 12, 15, 16];
+
+/// Base points of a non-yakuman hand, which every payment is a multiple of, `fu` is `None` for limit hands
+fn base_points(han: u32, fu: Option<u32>) -> u32 {
+    match han {
+        0..=4 => fu.map_or(2000, |fu| (fu << (han + 2)).min(2000)),
+        5 => 2000,
+        6 | 7 => 3000,
+        8..=10 => 4000,
+        11 | 12 => 6000,
+        _ => 8000,
+    }
+}
+
+/// Han from the count part of a tenhou yaku entry such as `Dora(2飜)`, `None` for yakuman
+fn yaku_han(yaku_count: &str) -> Option<u32> {
+    yaku_count.split(|c: char| !c.is_ascii_digit()).next()?.parse().ok()
+}
+
+impl PlayerInfo {
+    pub const DERIVED_HEADERS: &'static [&'static str] = &[
+        "agari_dealin_ratio",
+        "agari_rate",
+        "dealin_rate",
+        "avg_placement",
+        "placement_variance",
+        "avg_han",
+        "avg_fu",
+        "openness",
+        "avg_dora_indicators",
+    ];
+
+    /// Columns computed from the accumulated counts, written after `CSV_HEADERS`
+    pub fn derived_values(&self, precision: usize) -> Vec<String> {
+        // without any deal-ins the ratio is unbounded, the plain win count is the more useful number
+        let agari_dealin_ratio = if self.dealin_count == 0 {
+            self.agari_count as f64
+        } else {
+            self.agari_count as f64 / self.dealin_count as f64
+        };
+        [
+            agari_dealin_ratio,
+            self.agari_rate(),
+            self.dealin_rate(),
+            self.avg_placement(),
+            self.placement_variance(),
+            ratio(self.total_han, self.han_agari_count),
+            ratio(self.total_fu, self.fu_agari_count),
+            // every kyoku that ended with an open hand, won or not
+            ratio(self.open_count, self.kyoku_count),
+            // the starting indicator of every kyoku comes with StartKyoku rather than a Dora event
+            ratio(self.kyoku_count + self.total_dora_indicators_seen, self.kyoku_count),
+        ]
+        .into_iter()
+        .map(|x| fmt_ratio(x, precision))
+        .collect()
+    }
+
+    pub fn agari_rate(&self) -> f64 {
+        ratio(self.agari_count, self.kyoku_count)
+    }
+
+    pub fn dealin_rate(&self) -> f64 {
+        ratio(self.dealin_count, self.kyoku_count)
+    }
+
+    pub fn avg_placement(&self) -> f64 {
+        if self.placed_game_count == 0 {
+            0.0
+        } else {
+            self.total_placement / self.placed_game_count as f64
+        }
+    }
+
+    /// Population variance of the final placements, how far a player strays from their avg_placement game to game
+    pub fn placement_variance(&self) -> f64 {
+        if self.placed_game_count == 0 {
+            0.0
+        } else {
+            let avg_placement = self.avg_placement();
+            // rounding can push a spotless record slightly below zero
+            (self.total_placement_squared / self.placed_game_count as f64 - avg_placement * avg_placement).max(0.0)
+        }
+    }
+}
+
+/// Formatting shared by every derived column so that `--precision` applies uniformly
+fn fmt_ratio(x: f64, precision: usize) -> String {
+    format!("{x:.precision$}")
+}
+
+fn ratio(numerator: u32, denominator: u32) -> f64 {
+    if denominator == 0 {
+        0.0
+    } else {
+        numerator as f64 / denominator as f64
+    }
+}
+
+/// Nearest-rank percentile of already sorted values, `p` in 0..=1.
+fn percentile(sorted: &[f64], p: f64) -> Option<f64> {
+    let rank = (p * sorted.len() as f64).ceil() as usize;
+    sorted
+        .get(rank.saturating_sub(1).min(sorted.len().saturating_sub(1)))
+        .copied()
+}
+
+/// Fu of every win in a kyoku by winning seat, read from the point strings of the raw tenhou `log` entry since the
+/// parsed log drops them. Limit hands such as `満貫8000点` have no fu and are left out.
+fn kyoku_agari_fu(json_value: &serde_json::Value, kyoku_index: usize) -> [Option<u32>; 4] {
+    let mut agari_fu = [None; 4];
+    let Some(result) = json_value
+        .get("log")
+        .and_then(|log| log.get(kyoku_index))
+        .and_then(|kyoku| kyoku.as_array())
+        .and_then(|kyoku| kyoku.last())
+        .and_then(|result| result.as_array())
+    else {
+        return agari_fu;
+    };
+    if result.first().and_then(|kind| kind.as_str()) != Some("和了") {
+        return agari_fu;
+    }
+    // ["和了", deltas, [who, target, pao_who, point_string, yaku...], deltas, ...] with one detail per winner
+    for detail in result.iter().skip(2).step_by(2).filter_map(|detail| detail.as_array()) {
+        let who = detail.first().and_then(|who| who.as_u64());
+        let fu = detail
+            .get(3)
+            .and_then(|point_string| point_string.as_str())
+            .and_then(|point_string| point_string.split_once('符'))
+            .and_then(|(fu, _)| fu.parse().ok());
+        if let (Some(who), Some(fu)) = (who, fu) {
+            if let Some(seat_fu) = agari_fu.get_mut(who as usize) {
+                *seat_fu = Some(fu);
+            }
+        }
+    }
+    agari_fu
+}
+
+/// How players with equal final scores are ranked
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+enum TieMode {
+    /// The earlier seat takes the better rank, like Mahjong Soul and tenhou do
+    #[default]
+    Seat,
+    /// Tied players share the contested ranks equally, two players tied for 2nd both place 2.5th
+    Shared,
+}
+
+/// Which outputs the included players are written to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+enum OutputFormat {
+    /// --info-output
+    #[default]
+    Csv,
+    /// One json object per line on stdout
+    Ndjson,
+    /// Every format above in the same run
+    All,
+}
+
+/// Share of each final rank (1st to 4th) per seat from the `sc` array, every seat's shares add up to one game.
+fn final_placements(json_value: &serde_json::Value, tie_mode: TieMode) -> Option<[[f64; 4]; 4]> {
+    let scores = final_scores(json_value)?;
+    Some(std::array::from_fn(|seat| {
+        let ahead = (0..4).filter(|&other| scores[other] > scores[seat]).count();
+        let tied_before = (0..4).filter(|&other| scores[other] == scores[seat] && other < seat).count();
+        let tied = (0..4).filter(|&other| scores[other] == scores[seat]).count();
+        let mut shares = [0.0; 4];
+        match tie_mode {
+            TieMode::Seat => shares[ahead + tied_before] = 1.0,
+            TieMode::Shared => {
+                for share in &mut shares[ahead..ahead + tied] {
+                    *share = 1.0 / tied as f64;
+                }
+            }
+        }
+        shares
+    }))
+}
+
+/// Final score of every seat from the `sc` array, in the units of the log
+fn final_scores(json_value: &serde_json::Value) -> Option<[f64; 4]> {
+    let sc = json_value.get("sc")?.as_array()?;
+    let scores: Vec<f64> = sc.iter().step_by(2).map(|score| score.as_f64()).collect::<Option<_>>()?;
+    scores.try_into().ok()
+}
+
+/// Highest ron value over the waits of a tenpai 13-tile hand, `None` if not tenpai or no wait has a yaku
+fn best_agari_points(state: &PlayerState) -> Option<u32> {
+    if state.real_time_shanten() != 0 {
+        return None;
+    }
+    state
+        .waits
+        .iter()
+        .enumerate()
+        .filter(|&(_, &is_wait)| is_wait)
+        .filter_map(|(tile, _)| {
+            let Ok(Some(agari)) = state.calculate_agari(must_tile!(tile), false, &[]) else {
+                return None;
+            };
+            Some(agari.agari.point(false).ron as u32)
+        })
+        .max()
+}
+
+/// Shanten and ukeire left by every discard of a hand that is about to discard, indexed by the discarded tile.
+///
+/// The ukeire of a discard is the shanten-preserving acceptance of the remaining hand: every tile type that lowers its
+/// shanten when drawn, counted as `4 - tiles_seen`. Only discards keeping the lowest shanten are compared for
+/// total_ukeire_loss, giving up shanten is a choice about defense or value rather than efficiency.
+fn discard_ukeire(state: &PlayerState) -> [Option<(i8, u32)>; 34] {
+    let mut tehai = state.tehai;
+    let len_div3 = tehai.iter().sum::<u8>() / 3;
+    let mut discard_ukeire = [None; 34];
+    for discard in 0..34 {
+        if tehai[discard] == 0 {
+            continue;
+        }
+        tehai[discard] -= 1;
+        let shanten = calc_all(&tehai, len_div3);
+        let mut ukeire = 0;
+        for tile in 0..34 {
+            if tehai[tile] == 4 {
+                continue;
+            }
+            tehai[tile] += 1;
+            if calc_all(&tehai, len_div3) < shanten {
+                ukeire += 4 - state.tiles_seen[tile] as u32;
+            }
+            tehai[tile] -= 1;
+        }
+        discard_ukeire[discard] = Some((shanten, ukeire));
+        tehai[discard] += 1;
+    }
+    discard_ukeire
+}
+
+/// Live tile count of the wait left by every tenpai-keeping discard of a closed hand about to discard, indexed by the
+/// discarded tile.
+///
+/// Each tile type in tehai is removed in turn and the remaining tiles are checked for tenpai, an ankan leaves 11 of them.
+/// Their wait is every tile type that completes the hand, counted as `4 - tiles_seen` the same way as
+/// `total_riichi_wait`. Discards that break tenpai are `None`.
+fn tenpai_discard_waits(state: &PlayerState) -> [Option<u32>; 34] {
+    let mut tehai = state.tehai;
+    let len_div3 = tehai.iter().sum::<u8>() / 3;
+    let mut discard_waits = [None; 34];
+    for discard in 0..34 {
+        if tehai[discard] == 0 {
+            continue;
+        }
+        tehai[discard] -= 1;
+        if calc_all(&tehai, len_div3) == 0 {
+            let mut live_tiles = 0;
+            for tile in 0..34 {
+                if tehai[tile] == 4 {
+                    continue;
+                }
+                tehai[tile] += 1;
+                if calc_all(&tehai, len_div3) == -1 {
+                    live_tiles += 4 - state.tiles_seen[tile] as u32;
+                }
+                tehai[tile] -= 1;
+            }
+            discard_waits[discard] = Some(live_tiles);
+        }
+        tehai[discard] += 1;
+    }
+    discard_waits
+}
+
+/// Generates `Config`, the options a config file may set, and how it fills in `Args`. Listed options take the config
+/// value unless they were given on the command line, `optional` ones are the `Option` fields of `Args`.
+macro_rules! config_struct {
+    (values { $($field:ident: $ty:ty),* $(,)? } optional { $($opt_field:ident: $opt_ty:ty),* $(,)? }) => {
+        #[derive(Debug, Default, Deserialize)]
+        #[serde(deny_unknown_fields, rename_all = "kebab-case")]
+        struct Config {
+            $( $field: Option<$ty>, )*
+            $( $opt_field: Option<$opt_ty>, )*
+        }
+
+        impl Config {
+            fn apply(self, args: &mut Args, matches: &clap::ArgMatches) {
+                let from_command_line =
+                    |id: &str| matches.value_source(id) == Some(clap::parser::ValueSource::CommandLine);
+                $(
+                    if let Some(value) = self.$field.filter(|_| !from_command_line(stringify!($field))) {
+                        args.$field = value;
+                    }
+                )*
+                $(
+                    if let Some(value) = self.$opt_field.filter(|_| !from_command_line(stringify!($opt_field))) {
+                        args.$opt_field = Some(value);
+                    }
+                )*
+            }
+        }
+    };
+}
+
+config_struct! {
+    values {
+        log_level: String,
+        log_dir: PathBuf,
+        info_output: PathBuf,
+        yaku_output: PathBuf,
+        keep_untimed: bool,
+        split_round: bool,
+        by_seat_wind: bool,
+        seed: u64,
+        level: String,
+        tie_mode: TieMode,
+        precision: usize,
+        discards_output: PathBuf,
+        strict: bool,
+        no_cache: bool,
+        session: bool,
+        excel: bool,
+        ndjson: bool,
+        format: OutputFormat,
+        rated_only: bool,
+        precompute_danger: bool,
+        skip_danger: bool,
+        overfold_min_points: u32,
+        late_game_from: String,
+        efficiency: bool,
+        min_kyoku: u32,
+        exclude: Vec<String>,
+        focus_player: Vec<String>,
+        exclude_exact: Vec<String>,
+        include_exact: Vec<String>,
+    }
+    optional {
+        file: PathBuf,
+        min_game_seconds: u64,
+        self_only: String,
+        compare: (String, String),
+        debug_player: String,
+        monthly: PathBuf,
+        mjai_cache: PathBuf,
+        manifest: PathBuf,
+        checkpoint_every: u64,
+        yaku_aliases: PathBuf,
+        max_seconds_per_action: f64,
+        kyoku_heatmap: PathBuf,
+        dora_value: PathBuf,
+        missing_yaku: PathBuf,
+        discard_matrix: PathBuf,
+        margins: PathBuf,
+        timeline: PathBuf,
+        sample: f64,
+    }
+}
+
+/// Round wind and kyoku number of a label such as `S4`, in the order kyoku are played
+fn kyoku_order(label: &str) -> Option<(usize, u32)> {
+    let wind = "ESWN".find(label.get(..1)?)?;
+    let kyoku = label.get(1..)?.parse().ok().filter(|kyoku| (1..=4).contains(kyoku))?;
+    Some((wind, kyoku))
+}
+
+fn parse_kyoku_label(label: &str) -> std::result::Result<String, String> {
+    match kyoku_order(label) {
+        Some(_) => Ok(label.to_owned()),
+        None => Err("expected a round wind letter and kyoku number such as S4".to_owned()),
+    }
+}
+
+fn parse_fraction(fraction: &str) -> std::result::Result<f64, String> {
+    match fraction.parse::<f64>() {
+        Ok(fraction) if (0.0..=1.0).contains(&fraction) => Ok(fraction),
+        _ => Err("expected a number from 0 to 1".to_owned()),
+    }
+}
+
+fn parse_compare(names: &str) -> std::result::Result<(String, String), String> {
+    match names.split_once(',') {
+        Some((left, right)) if !left.is_empty() && !right.is_empty() && !right.contains(',') => {
+            Ok((left.to_owned(), right.to_owned()))
+        }
+        _ => Err("expected two player names separated by a comma".to_owned()),
+    }
+}
+
+/// Config file read from the working directory when --config is not given
+const DEFAULT_CONFIG: &str = "yil-reviewer.toml";
+
+/// Manifest kept in --log-dir when --manifest is not given
+const DEFAULT_MANIFEST: &str = ".cache.json";
+
+#[derive(Debug, clap::Subcommand)]
+enum Command {
+    /// Sum the players of previously written info csv files into one, without reading any logs. Players below
+    /// --min-kyoku or filtered by name in a shard are missing from it, so write the shards with `--min-kyoku 0` and
+    /// leave the filters to the merge. Only info.csv is merged, yaku.csv and the other outputs are not
+    Merge {
+        inputs: Vec<PathBuf>,
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Print every player name in the logs with their kyoku count, without computing any stats
+    List,
+    /// Write the mjai events of a single kyoku of a log as ndjson, after the start_game event of the log
+    ExportKyoku {
+        file: PathBuf,
+        /// Position of the kyoku in the log, starting at 0
+        kyoku_index: usize,
+        /// Written to stdout when not given
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+/// Aggregate per-player statistics from downloaded tenhou logs
+#[derive(Debug, Parser)]
+pub struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// TOML file with defaults for the options below using their flag names, e.g. `log-dir = "./archive"`, options
+    /// given on the command line override it. Defaults to ./yil-reviewer.toml if it exists
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+    /// Diagnostics to show on stderr, a level such as `warn` or `debug` or a full filter such as
+    /// `tournament_review=debug`
+    #[arg(long, default_value = "info")]
+    log_level: String,
+    /// Directory containing the downloaded logs
+    #[arg(long, default_value = "./downloads")]
+    log_dir: PathBuf,
+    /// Process only this log instead of the whole log directory
+    #[arg(long, value_name = "PATH")]
+    file: Option<PathBuf>,
+    /// Output path for per-player stats
+    #[arg(long, default_value = "./info.csv")]
+    info_output: PathBuf,
+    /// Output path for per-player yaku counts
+    #[arg(long, default_value = "./yaku.csv")]
+    yaku_output: PathBuf,
+    /// Skip logs whose mjshead duration is shorter than this many seconds
+    #[arg(long, value_name = "N")]
+    min_game_seconds: Option<u64>,
+    /// Keep logs without mjshead timing instead of skipping them when --min-game-seconds is set
+    #[arg(long, requires = "min_game_seconds")]
+    keep_untimed: bool,
+    /// Only read this share of the logs in --log-dir, each file being kept with this probability
+    #[arg(long, value_name = "FRACTION", value_parser = parse_fraction)]
+    sample: Option<f64>,
+    /// Seed of --sample, the same seed keeps the same files whatever order the directory lists them in
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+    /// Also write stats restricted to each round wind, e.g. info_east_round.csv and info_south_round.csv
+    #[arg(long)]
+    split_round: bool,
+    /// Also write stats restricted to each seat wind of the player in the kyoku, e.g. info_east_seat.csv holds the
+    /// kyoku they were dealer in
+    #[arg(long)]
+    by_seat_wind: bool,
+    /// Only process logs whose `rule.disp` lobby and rule description matches exactly, e.g. "Tournament South Red". The
+    /// level column of the outputs lists the descriptions of every game of a player either way
+    #[arg(long, default_value = "all")]
+    level: String,
+    /// How equal final scores are ranked for avg_placement and the rank counts. `shared` gives tied players the mean
+    /// of the contested ranks, which changes avg_placement compared to the default
+    #[arg(long, value_enum, default_value_t = TieMode::Seat)]
+    tie_mode: TieMode,
+    /// Only process logs one of these players took part in, every seat of those logs is still counted. Can be given
+    /// multiple times
+    #[arg(long, value_name = "NAME")]
+    focus_player: Vec<String>,
+    /// Only track the seat of this player in the processed logs, skipping the state updates of their opponents.
+    /// Opponents then get no stats besides their yaku, use together with --focus-player
+    #[arg(long, value_name = "NAME", requires = "focus_player")]
+    self_only: Option<String>,
+    /// Only write players with at least this many kyoku
+    #[arg(long, value_name = "N", default_value_t = 101)]
+    min_kyoku: u32,
+    /// Leave players whose name contains this out of the written players, can be given multiple times. Giving it
+    /// replaces the default, `--exclude ''` excludes nobody
+    #[arg(long, value_name = "TEXT", default_value = "ashlen")]
+    exclude: Vec<String>,
+    /// Leave the player with exactly this name out of the written players, over every other name filter
+    #[arg(long, value_name = "NAME")]
+    exclude_exact: Vec<String>,
+    /// Keep the player with exactly this name even if --exclude matches it, the kyoku count minimum still applies
+    #[arg(long, value_name = "NAME")]
+    include_exact: Vec<String>,
+    /// Print every stat of two players side by side with their difference after processing, as `NAME,NAME`
+    #[arg(long, value_name = "NAME,NAME", value_parser = parse_compare)]
+    compare: Option<(String, String)>,
+    /// Decimal places of derived columns such as rates and averages
+    #[arg(long, value_name = "N", default_value_t = 4)]
+    precision: usize,
+    /// Log a line per kyoku for this player at the info level while processing, logs reused from the manifest are not
+    /// traced
+    #[arg(long, value_name = "NAME")]
+    debug_player: Option<String>,
+    /// With --file and --debug-player, where to write every discard of that player with its danger against each
+    /// opponent in riichi
+    #[arg(long, default_value = "./discards.csv")]
+    discards_output: PathBuf,
+    /// Also write stats of the included players per calendar month (UTC) of the game start
+    #[arg(long, value_name = "PATH")]
+    monthly: Option<PathBuf>,
+    /// Directory to cache converted mjai events in, reused while the log content is unchanged
+    #[arg(long, value_name = "DIR")]
+    mjai_cache: Option<PathBuf>,
+    /// Abort on the first log that fails to process instead of skipping it, inconsistent Hora deltas count as failures
+    #[arg(long)]
+    strict: bool,
+    /// Manifest of per-file contributions, only new or modified logs are processed again. Defaults to `.cache.json` in
+    /// --log-dir unless --file is given
+    #[arg(long)]
+    manifest: Option<PathBuf>,
+    /// Reprocess every log instead of reusing the manifest, which is still written for the next run
+    #[arg(long)]
+    no_cache: bool,
+    /// Save the manifest after every N logs as well, so that a crashed run picks up from the last save
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    checkpoint_every: Option<u64>,
+    /// Treat every log as one continuous session ordered by game start, streaks carry over from one game to the next
+    #[arg(long)]
+    session: bool,
+    /// TOML table renaming tenhou yaku names in yaku.csv, e.g. `"Red Dragon" = "Yakuhai"`, unlisted yaku keep their name
+    #[arg(long, value_name = "PATH")]
+    yaku_aliases: Option<PathBuf>,
+    /// Start every csv with a UTF-8 BOM so that Excel shows non-ascii player names correctly
+    #[arg(long)]
+    excel: bool,
+    /// Print the included players as one json object per line to stdout instead of writing info.csv, progress moves
+    /// to stderr. Short for --format ndjson
+    #[arg(long)]
+    ndjson: bool,
+    /// Only process rated games. Tenhou logs count when they are from the public lobby (`"lobby": 0`), converted Mahjong
+    /// Soul logs when `mjshead.config.meta.mode_id` is a four player ranked room: 2 and 3 bronze, 5 and 6 silver, 8
+    /// and 9 gold, 11 and 12 jade, 15 and 16 throne, east and south each. Friendly rooms and tournaments carry a
+    /// `room_id` or `contest_uid` instead and are skipped
+    #[arg(long)]
+    rated_only: bool,
+    /// Compute the danger before every own discard on a second thread, overlapping it with the rest of the event
+    /// loop of the seat
+    #[arg(long)]
+    precompute_danger: bool,
+    /// Outputs of the included players, `all` writes info.csv and prints the ndjson rows from a single pass
+    #[arg(long, value_enum, default_value_t = OutputFormat::Csv, conflicts_with = "ndjson")]
+    format: OutputFormat,
+    /// Skip the danger calculation before every own discard, ippatsu_brazen_count, genbutsu_dahai_count, overfold_count
+    /// and the push, fold and safe tile stats stay zero and the discards output has no danger
+    #[arg(long)]
+    skip_danger: bool,
+    /// Leave a player's game out of seconds_played and action_count when it averages more seconds per action than
+    /// this, which catches afk and disconnected players while keeping the rest of their stats
+    #[arg(long, value_name = "SECONDS")]
+    max_seconds_per_action: Option<f64>,
+    /// Also write won, dealt in and played kyoku of the included players per kyoku number, E1 through S4 and beyond
+    #[arg(long, value_name = "PATH")]
+    kyoku_heatmap: Option<PathBuf>,
+    /// Ron value of a dama tenpai hand from which folding against a riichi counts towards overfold_count, 8000 is a
+    /// non-dealer mangan. One-shanten hands are never counted, whatever they could be worth
+    #[arg(long, default_value_t = 8000)]
+    overfold_min_points: u32,
+    /// First kyoku of the late game for risky_lead_riichi_count, such as the default S4 for the last kyoku of a
+    /// hanchan or E4 for a tonpuusen. Extra rounds after it count as late game too
+    #[arg(long, value_name = "KYOKU", default_value = "S4", value_parser = parse_kyoku_label)]
+    late_game_from: String,
+    /// Compare every own discard outside of riichi and not facing one against the best discard keeping the same
+    /// shanten for total_ukeire_loss, slow since the ukeire of every possible discard is recomputed. Also fills
+    /// wait_widening_riichi_count
+    #[arg(long)]
+    efficiency: bool,
+    /// Also write wins and their summed score of the included players per dora count (dora, red fives and ura)
+    #[arg(long, value_name = "PATH")]
+    dora_value: Option<PathBuf>,
+    /// Also write, per included player, every yaku seen in the logs that they never won with, most common first
+    #[arg(long, value_name = "PATH")]
+    missing_yaku: Option<PathBuf>,
+    /// Also write, per included player, a row of own discard counts for each of the 34 tile types, red fives counted as
+    /// plain fives
+    #[arg(long, value_name = "PATH")]
+    discard_matrix: Option<PathBuf>,
+    /// Also write, per processed game, the names of the first and last place and the final score gaps between 1st and
+    /// 2nd and between 3rd and 4th, in the units of the log's `sc` scores
+    #[arg(long, value_name = "PATH")]
+    margins: Option<PathBuf>,
+    /// Also write the score delta of every kyoku of each --focus-player with their running total, ordered by game start
+    #[arg(long, value_name = "PATH", requires = "focus_player")]
+    timeline: Option<PathBuf>,
+    /// Text file of tenhou.net/0/?log=... links, logs missing from --log-dir are downloaded there before processing
+    #[cfg(feature = "net")]
+    #[arg(long, value_name = "PATH")]
+    urls: Option<PathBuf>,
+}
+
+/// Everything a single log contributes to the outputs
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct LogStats {
+    /// Unix timestamp of the game start from mjshead
+    start_time: Option<u64>,
+    players_info: HashMap<String, PlayerInfo>,
+    yaku_info: HashMap<String, HashMap<String, u32>>,
+    /// Stats restricted to a subset of kyoku, keyed by the suffix of their output file
+    split_info: BTreeMap<String, HashMap<String, PlayerInfo>>,
+    /// Won kyoku runs of every player in this log, joined across logs by --session
+    agari_streaks: HashMap<String, AgariStreak>,
+    /// Won, dealt in and played kyoku of every player per kyoku such as `E1`, only kept with --kyoku-heatmap
+    kyoku_heatmap: PlayerCells<3>,
+    /// Wins and their summed score delta of every player per dora count bucket, only kept with --dora-value
+    dora_value: PlayerCells<2>,
+    /// Own discards of every player per tile type, 34 long, only kept with --discard-matrix
+    discard_matrix: HashMap<String, Vec<u32>>,
+    /// Names and final scores from first to last place, empty without final scores in the log
+    final_standings: Vec<(String, f64)>,
+    /// Score delta of every kyoku of each --focus-player, only kept with --timeline
+    kyoku_deltas: HashMap<String, Vec<i32>>,
+    /// `rule.disp` of the games of every player, the level column of the outputs
+    levels: PlayerLevels,
+}
+
+/// Distinct levels every player was seen in, a player moving between lobbies has several
+type PlayerLevels = HashMap<String, BTreeSet<String>>;
+
+fn merge_levels(into: &mut PlayerLevels, from: &PlayerLevels) {
+    for (name, levels) in from {
+        into.entry(name.clone()).or_default().extend(levels.iter().cloned());
+    }
+}
+
+/// The level column of a player, their levels joined by `; `
+fn level_label(levels: &PlayerLevels, name: &str) -> String {
+    levels
+        .get(name)
+        .map(|levels| Vec::from_iter(levels.iter().map(String::as_str)).join("; "))
+        .unwrap_or_default()
+}
+
+/// Counters of every player per bucket label, the rows of the smaller cross-tab outputs
+type PlayerCells<const N: usize> = HashMap<String, BTreeMap<String, [u32; N]>>;
+
+fn merge_cells<const N: usize>(into: &mut PlayerCells<N>, from: &PlayerCells<N>) {
+    for (name, cells) in from {
+        let collected_cells = into.entry(name.clone()).or_default();
+        for (label, cell) in cells {
+            let collected_cell = collected_cells.entry(label.clone()).or_insert([0; N]);
+            for (collected, count) in collected_cell.iter_mut().zip(cell) {
+                *collected += count;
+            }
+        }
+    }
+}
+
+impl LogStats {
+    fn merge(&mut self, other: &LogStats) {
+        for (name, info) in &other.players_info {
+            *self.players_info.entry(name.clone()).or_default() += info;
+        }
+        for (name, yaku) in &other.yaku_info {
+            let collected_yaku = self.yaku_info.entry(name.clone()).or_default();
+            for (yaku_name, count) in yaku {
+                *collected_yaku.entry(yaku_name.clone()).or_default() += count;
+            }
+        }
+        for (split, players_info) in &other.split_info {
+            let split_info = self.split_info.entry(split.clone()).or_default();
+            for (name, info) in players_info {
+                *split_info.entry(name.clone()).or_default() += info;
+            }
+        }
+        merge_cells(&mut self.kyoku_heatmap, &other.kyoku_heatmap);
+        merge_levels(&mut self.levels, &other.levels);
+        merge_cells(&mut self.dora_value, &other.dora_value);
+        for (name, discards) in &other.discard_matrix {
+            let collected_discards = self.discard_matrix.entry(name.clone()).or_insert_with(|| vec![0; 34]);
+            for (collected, count) in collected_discards.iter_mut().zip(discards) {
+                *collected += count;
+            }
+        }
+    }
+}
+
+/// Per-file contributions of a previous run, keyed by log path
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    /// Options the contributions were computed with from `Args::manifest_settings`, any change invalidates every entry
+    settings: String,
+    files: HashMap<String, ManifestEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ManifestEntry {
+    size: u64,
+    /// Modification time in seconds since the unix epoch
+    modified: u64,
+    /// `None` if the log was skipped by a filter
+    stats: Option<LogStats>,
+}
+
+impl Manifest {
+    fn load(path: &Path, settings: &str) -> Result<Manifest> {
+        if !path.exists() {
+            return Ok(Manifest::default());
+        }
+        let json_string = std::fs::read_to_string(path).map_err(YilError::io(path))?;
+        // manifests written by an older version or with other settings are rebuilt from scratch
+        match serde_json::from_str::<Manifest>(&json_string) {
+            Ok(manifest) if manifest.settings == settings => Ok(manifest),
+            Ok(_) => {
+                warn!(?path, "manifest settings changed, reprocessing every log");
+                Ok(Manifest::default())
+            }
+            Err(err) => {
+                warn!(?path, %err, "manifest is unreadable, reprocessing every log");
+                Ok(Manifest::default())
+            }
+        }
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        Manifest::write(path, &self.settings, self.files.iter())
+    }
+
+    /// Saves the entries of this run so far together with the previous entries not visited yet, a run that dies
+    /// afterwards resumes from here instead of reprocessing everything
+    fn save_checkpoint(&self, path: &Path, pending: &Manifest) -> Result<()> {
+        Manifest::write(path, &self.settings, self.files.iter().chain(&pending.files))
+    }
+
+    fn write<'a>(path: &Path, settings: &str, files: impl Iterator<Item = (&'a String, &'a ManifestEntry)>) -> Result<()> {
+        #[derive(Serialize)]
+        struct ManifestRef<'a> {
+            settings: &'a str,
+            files: HashMap<&'a String, &'a ManifestEntry>,
+        }
+
+        let json_string = serde_json::to_string(&ManifestRef {
+            settings,
+            files: files.collect(),
+        })?;
+        // written next to the manifest and renamed over it so that a crash mid-write keeps the previous one
+        let partial_path = path.with_extension("partial");
+        std::fs::write(&partial_path, json_string).map_err(YilError::io(&partial_path))?;
+        std::fs::rename(&partial_path, path).map_err(YilError::io(path))
+    }
+}
+
+/// Per-kyoku facts about one seat, reset at every StartKyoku
+#[derive(Debug, Default)]
+struct KyokuTracker {
+    label: String,
+    haipai_shanten: i32,
+    /// Self score change over the kyoku, Hora and Ryukyoku deltas minus the own riichi deposit
+    self_delta: i32,
+    won: bool,
+    dealt_in: bool,
+    /// Another player won without self being the target
+    other_hora: bool,
+    /// Another player won by tsumo
+    other_tsumo: bool,
+    /// Count of Dahai events from every player so far
+    discard_count: u32,
+    /// Count of Tsumo events from every player so far, rinshan draws included
+    tsumo_count: u32,
+    /// Value of `discard_count` when self first became tenpai
+    self_tenpai_at: Option<u32>,
+    /// Own discard count when self first became tenpai
+    self_tenpai_turn: Option<u32>,
+    /// Value of `discard_count` at the last accepted opponent riichi
+    opponent_tenpai_at: Option<u32>,
+    /// Opponent seats with an accepted riichi
+    opponent_riichi: [bool; 4],
+    /// Opponent seats that were already in riichi when self riichi was accepted
+    chased_seats: [bool; 4],
+    /// One of `chased_seats` won by tsumo
+    chased_riichi_tsumo: bool,
+    /// Own discards the tile type of the last own discard was held through before it
+    last_dahai_held_turns: u32,
+    last_dahai_tsumogiri: bool,
+    /// The kyoku ended in a Ryukyoku event, exhaustive or abortive
+    drawn: bool,
+    /// Count of own Tsumo events since self riichi was accepted
+    own_tsumo_since_riichi: Option<u32>,
+    /// Scores of every seat from StartKyoku with the riichi deposits of this kyoku taken off
+    scores: [i32; 4],
+    /// Self was dama tenpai for at least --overfold-min-points when an opponent riichi was accepted
+    valuable_tenpai_facing_riichi: bool,
+    /// Self discarded a tile that is not genbutsu against every riichi since `valuable_tenpai_facing_riichi`
+    pushed_after_riichi: bool,
+    /// Self went back from tenpai to one-shanten or worse since `valuable_tenpai_facing_riichi`
+    broke_tenpai_after_riichi: bool,
+    /// Best ron value of the current tenpai with yaku after own discards, `None` while not tenpai
+    tenpai_value: Option<u32>,
+    /// Highest `tenpai_value` of any tenpai that self discarded out of
+    left_tenpai_value: Option<u32>,
+    /// Per opponent in riichi, count of own discards since their riichi while every one was genbutsu against them,
+    /// `None` once a discard was not or when self is in riichi
+    folding_against: [Option<u32>; 4],
+    /// Count of own Tsumo events since our last chi, pon or kan
+    own_tsumo_since_call: Option<u32>,
+    /// Tile of the last own Dahai or Kakan, the one an opponent can ron or rob
+    last_ronnable_tile: Option<Tile>,
+    /// Tile of the last Tsumo, Dahai or Kakan of every seat, the one a Hora right after it wins on
+    last_tile: [Option<Tile>; 4],
+    /// Per opponent in riichi, bit mask of tile types in our hand at some point since their riichi
+    held_since_riichi: [u64; 4],
+    /// Per opponent in riichi, bit mask of tile types we discarded since their riichi
+    discarded_since_riichi: [u64; 4],
+    /// Per seat, bit mask of tile types with zero danger towards them before any own discard so far
+    ever_safe_against: [u64; 4],
+    /// Per seat, the last own discard was dangerous towards them after having been safe before an earlier discard
+    last_dahai_stale_safe: [bool; 4],
+    /// Per seat, the hand left by the last own discard held a tile with zero danger towards them
+    last_dahai_had_safe: [bool; 4],
+    /// The last own Tsumo completed the hand, but a ron on the same tile would have had no yaku
+    yakuless_on_ron: bool,
+}
+
+impl KyokuTracker {
+    fn trace(&self, name: &str, riichi: bool) {
+        let result = if self.won {
+            "win"
+        } else if self.dealt_in {
+            "deal-in"
+        } else if self.other_hora {
+            "opponent win"
+        } else {
+            "draw"
+        };
+        // through tracing so that it lands on stderr next to the progress instead of in the ndjson rows
+        info!(
+            player = %name,
+            kyoku = %self.label,
+            result,
+            delta = self.self_delta,
+            riichi,
+            haipai_shanten = self.haipai_shanten,
+            "kyoku trace"
+        );
+    }
+}
+
+/// `YYYY-MM` of a unix timestamp in UTC
+fn year_month(unix_seconds: u64) -> String {
+    // days since 0000-03-01 so that leap days fall at the end of each year
+    let days = (unix_seconds / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}")
+}
+
+fn wind_name(wind: Tile) -> &'static str {
+    match wind.as_usize() {
+        27 => "east",
+        28 => "south",
+        29 => "west",
+        _ => "north",
+    }
+}
+
+/// Suffixes of the split outputs a kyoku is counted in besides the overall stats
+fn kyoku_splits(args: &Args, bakaze: Tile, jikaze: Tile) -> Vec<String> {
+    let mut splits = Vec::new();
+    if args.split_round {
+        splits.push(format!("{}_round", wind_name(bakaze)));
+    }
+    if args.by_seat_wind {
+        splits.push(format!("{}_seat", wind_name(jikaze)));
+    }
+    splits
+}
+
+/// 64 bit FNV-1a, stable across runs and toolchains unlike `DefaultHasher`
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Converts a log to mjai events, going through `cache_dir` when given. Cache files hold the hash of the log source on
+/// their first line followed by one event per line, a hash mismatch or unreadable cache converts the log again.
+fn mjai_events(log: &Log, json_string: &str, path: &Path, cache_dir: Option<&Path>) -> Result<Vec<Event>> {
+    let Some(cache_dir) = cache_dir else {
+        return riichi::convlog::tenhou_to_mjai(log).map_err(|err| YilError::MjaiConvert(err.into()));
+    };
+    let hash = format!("{:016x}", fnv1a(json_string.as_bytes()));
+    let cache_path = cache_dir.join(path.file_name().unwrap_or_default()).with_extension("jsonl");
+    if let Ok(cached) = std::fs::read_to_string(&cache_path) {
+        let mut lines = cached.lines();
+        if lines.next() == Some(hash.as_str()) {
+            if let Ok(events) = lines.map(serde_json::from_str::<Event>).collect::<Result<Vec<_>, _>>() {
+                return Ok(events);
+            }
+        }
+    }
+
+    let events = riichi::convlog::tenhou_to_mjai(log).map_err(|err| YilError::MjaiConvert(err.into()))?;
+    let mut contents = hash;
+    contents.push('\n');
+    for event in &events {
+        contents += &serde_json::to_string(event)?;
+        contents.push('\n');
+    }
+    std::fs::create_dir_all(cache_dir).map_err(YilError::io(cache_dir))?;
+    std::fs::write(&cache_path, contents).map_err(YilError::io(&cache_path))?;
+    Ok(events)
+}
+
+/// Checks that the deltas of every Hora event sum to the riichi sticks collected by the winner, warning about (or with
+/// `strict` failing on) logs that were corrupted or misconverted since they would silently skew every score stat
+fn check_hora_deltas(events: &[Event], path: &Path, strict: bool) -> Result<()> {
+    let mut kyoku_index = 0;
+    let mut sticks = 0;
+    for event in events {
+        match event {
+            Event::StartKyoku { kyotaku, .. } => {
+                kyoku_index += 1;
+                sticks = *kyotaku as i32;
+            }
+            Event::ReachAccepted { .. } => sticks += 1,
+            Event::Hora {
+                deltas: Some(deltas), ..
+            } => {
+                let delta_sum = deltas.iter().sum::<i32>();
+                if delta_sum != sticks * 1000 {
+                    let message = format!(
+                        "Hora deltas {deltas:?} in kyoku {kyoku_index} of {path:?} sum to {delta_sum} instead of {}",
+                        sticks * 1000
+                    );
+                    if strict {
+                        return Err(YilError::InconsistentLog(message));
+                    }
+                    warn!("{message}");
+                }
+                // on a multi ron only the first winner takes the sticks
+                sticks = 0;
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Accumulates the stats of a single log, `None` if the log is skipped by a filter
+pub fn process_log(path: &Path, args: &Args, yaku_aliases: &BTreeMap<String, String>) -> Result<Option<LogStats>> {
+    let json_string = std::fs::read_to_string(path).map_err(YilError::io(path))?;
+    let log = Log::from_json_str(&json_string).map_err(|err| YilError::TenhouParse(err.into()))?;
+    // every seat below is looked up by name, sanma and truncated logs would index past the end
+    if log.names.len() < 4 {
+        warn!(name_count = log.names.len(), "skipping log without four player names");
+        return Ok(None);
+    }
+    if !args.focus_player.is_empty() && !log.names.iter().any(|name| args.focus_player.contains(name)) {
+        return Ok(None);
+    }
+
+    let json_value: serde_json::Value = serde_json::from_str(&json_string).map_err(|err| YilError::TenhouParse(err.into()))?;
+    let (start_time, duration) = if let Some(mjshead) = json_value.get("mjshead") {
+        let mjshead_time = |key: &str| {
+            mjshead
+                .get(key)
+                .and_then(|time| time.as_u64())
+                .ok_or_else(|| YilError::TenhouParse(format!("no mjshead.{key}").into()))
+        };
+        let start_time = mjshead_time("start_time")?;
+        let end_time = mjshead_time("end_time")?;
+        (Some(start_time), Some(end_time - start_time))
+    } else {
+        (None, None)
+    };
+    if let Some(min_game_seconds) = args.min_game_seconds {
+        // short games are usually disconnects and would skew seconds_played and action_count
+        if !duration.map_or(args.keep_untimed, |duration| duration >= min_game_seconds) {
+            info!("skipping short or untimed game");
+            return Ok(None);
+        }
+    }
+
+    if args.rated_only {
+        let rated = match json_value.get("mjshead") {
+            // the converter writes lobby 0 for every Mahjong Soul game, only the match mode tells ranked games apart
+            Some(mjshead) => mjshead
+                .pointer("/config/meta/mode_id")
+                .and_then(|mode_id| mode_id.as_u64())
+                .is_some_and(|mode_id| RANKED_MODE_IDS.contains(&mode_id)),
+            // lobby 0 is the public ranked lobby, every other number is a private lobby that does not affect rating
+            None => json_value.get("lobby").and_then(|lobby| lobby.as_u64()) == Some(0),
+        };
+        if !rated {
+            info!("skipping unrated game");
+            return Ok(None);
+        }
+    }
+
+    let level = json_value
+        .get("rule")
+        .and_then(|rule| rule.get("disp"))
+        .and_then(|disp| disp.as_str());
+    if args.level != "all" && level != Some(args.level.as_str()) {
+        info!("skipping game from another level");
+        return Ok(None);
+    }
+
+    let events = mjai_events(&log, &json_string, path, args.mjai_cache.as_deref())?;
+    check_hora_deltas(&events, path, args.strict)?;
+    let mut stats = LogStats {
+        start_time,
+        ..Default::default()
+    };
+    if let Some(level) = level {
+        for name in &log.names {
+            stats.levels.entry(name.clone()).or_default().insert(level.to_owned());
+        }
+    }
+
+    // stats from the tenhou hora details, folded into each seat's kyoku stats in the event loop
+    let mut hora_infos: Vec<[PlayerInfo; 4]> = vec![Default::default(); log.kyokus.len()];
+    // share of each win's hand value left without its ura dora
+    let mut ura_free_shares: Vec<[f64; 4]> = vec![[1.0; 4]; log.kyokus.len()];
+    for (kyoku_index, tenhou_kyoku) in log.kyokus.iter().enumerate() {
+        match &tenhou_kyoku.end_status {
+            EndStatus::Hora { details } => {
+                let agari_fu = kyoku_agari_fu(&json_value, kyoku_index);
+                // a kyoku ends on a single discard, so every ron of a double or triple ron has the same target
+                let mut ron_count = [0; 4];
+                for hora_detail in details.iter().filter(|hora_detail| hora_detail.who != hora_detail.target) {
+                    ron_count[hora_detail.target as usize] += 1;
+                }
+                for (seat, &count) in ron_count.iter().enumerate() {
+                    if count >= 2 {
+                        hora_infos[kyoku_index][seat].multi_ron_dealin_count += 1;
+                    }
+                }
+                for hora_detail in details {
+                    let actor_name = log.names[hora_detail.who as usize].clone();
+                    let info = &mut hora_infos[kyoku_index][hora_detail.who as usize];
+                    let collected_yaku = stats.yaku_info.entry(actor_name).or_default();
+                    let mut dora_han = 0;
+                    let mut ura_han = 0;
+                    let mut hand_yaku_count = 0;
+                    for yaku in &hora_detail.yaku {
+                        let Some((yaku_name, yaku_count)) = yaku.split_once('(') else {
+                            return Err(YilError::TenhouParse(format!("invalid tenhou yaku name {yaku:?}").into()));
+                        };
+                        if yaku_name == "Ura Dora" && yaku_count.starts_with('0') {
+                            continue;
+                        }
+                        if DORA_YAKU.contains(&yaku_name) {
+                            dora_han += yaku_han(yaku_count).unwrap_or(0);
+                            if yaku_name == "Ura Dora" {
+                                ura_han += yaku_han(yaku_count).unwrap_or(0);
+                            }
+                        } else {
+                            hand_yaku_count += 1;
+                        }
+                        let yaku_key = yaku_aliases.get(yaku_name).map_or(yaku_name, String::as_str);
+                        *collected_yaku.entry(yaku_key.to_owned()).or_default() += 1;
+                    }
+                    let has_yaku = |name: &str| {
+                        hora_detail
+                            .yaku
+                            .iter()
+                            .any(|yaku| yaku.split_once('(').is_some_and(|(yaku_name, _)| yaku_name == name))
+                    };
+                    if has_yaku("Pinfu") {
+                        info.pinfu_agari_count += 1;
+                        if has_yaku("Riichi") || has_yaku("Double Riichi") {
+                            info.pinfu_riichi_agari_count += 1;
+                        }
+                    }
+                    // the open variants share the name and only differ in han
+                    if has_yaku("Mixed Triple Sequence") {
+                        info.sanshoku_agari_count += 1;
+                    }
+                    if has_yaku("Pure Straight") {
+                        info.ittsuu_agari_count += 1;
+                    }
+                    if has_yaku("Ippatsu") {
+                        if hora_detail.who == hora_detail.target {
+                            info.ippatsu_tsumo_count += 1;
+                        } else {
+                            info.ippatsu_ron_count += 1;
+                        }
+                    }
+                    if dora_han == 0 {
+                        info.no_dora_agari_count += 1;
+                    }
+                    if hand_yaku_count == 1 {
+                        info.single_yaku_agari_count += 1;
+                    }
+                    let hand_han: Option<u32> = hora_detail
+                        .yaku
+                        .iter()
+                        .map(|yaku| yaku.split_once('(').and_then(|(_, yaku_count)| yaku_han(yaku_count)))
+                        .sum();
+                    if let Some(hand_han) = hand_han {
+                        info.total_han += hand_han;
+                        info.han_agari_count += 1;
+                        if ura_han > 0 {
+                            let fu = agari_fu[hora_detail.who as usize];
+                            ura_free_shares[kyoku_index][hora_detail.who as usize] =
+                                base_points(hand_han - ura_han, fu.or(Some(30))) as f64 / base_points(hand_han, fu) as f64;
+                        }
+                    }
+                    if let Some(fu) = agari_fu[hora_detail.who as usize] {
+                        info.total_fu += fu;
+                        info.fu_agari_count += 1;
+                    }
+                    info.total_dora_in_wins += dora_han;
+                }
+            }
+            EndStatus::Ryukyoku { .. } => {}
+        }
+    }
+
+    let placements = final_placements(&json_value, args.tie_mode);
+    if let Some(scores) = final_scores(&json_value) {
+        let mut standings: Vec<(String, f64)> = log.names.iter().cloned().zip(scores).collect();
+        // a stable sort keeps equal scores in seat order
+        standings.sort_by(|(_, l), (_, r)| r.total_cmp(l));
+        stats.final_standings = standings;
+    }
+    let final_kyoku_scores = events.iter().rev().find_map(|event| match event {
+        Event::StartKyoku { scores, .. } => Some(*scores),
+        _ => None,
+    });
+    // riichi_induced_fold_estimate of each seat per kyoku, known only once every opponent was processed
+    let mut induced_folds = vec![[0; 4]; log.kyokus.len()];
+    let mut seat_kyoku_infos = Vec::new();
+    for player_id in 0..4 {
+        let name = log.names[player_id].clone();
+        if args.self_only.as_ref().is_some_and(|self_only| *self_only != name) {
+            continue;
+        }
+        if let Some(placements) = placements {
+            let game_info = stats.players_info.entry(name.clone()).or_default();
+            game_info.placed_game_count += 1;
+            // a shared tie counts as a single game at the mean of the tied ranks
+            let placement = placements[player_id]
+                .into_iter()
+                .enumerate()
+                .map(|(rank, share)| (rank + 1) as f64 * share)
+                .sum::<f64>();
+            game_info.total_placement += placement;
+            game_info.total_placement_squared += placement * placement;
+            game_info.rank_1_count += placements[player_id][0];
+            game_info.rank_2_count += placements[player_id][1];
+            game_info.rank_3_count += placements[player_id][2];
+            game_info.rank_4_count += placements[player_id][3];
+            if let Some(scores) = final_kyoku_scores {
+                let last_before_final = (0..4)
+                    .all(|seat| scores[seat] > scores[player_id] || (scores[seat] == scores[player_id] && seat <= player_id));
+                if last_before_final {
+                    game_info.last_before_final_count += 1;
+                    if placements[player_id][3] == 0.0 {
+                        game_info.last_place_escapes += 1;
+                    }
+                }
+            }
+        }
+
+        // stats of the current kyoku, kept with its splits at EndKyoku and added to the overall and split stats once
+        // the whole game was seen
+        let mut info = PlayerInfo::default();
+        let mut kyoku_infos: Vec<(Vec<String>, PlayerInfo)> = Vec::new();
+        let mut kyoku_label = String::new();
+        let mut kyoku_index = 0;
+        let mut splits = Vec::new();
+
+        // with --precompute-danger the danger before every own discard is computed on a thread replaying the same
+        // events, running ahead of the accumulation below
+        let danger_receiver = (args.precompute_danger && !args.skip_danger).then(|| {
+            let (sender, receiver) = std::sync::mpsc::channel();
+            let events = events.clone();
+            std::thread::spawn(move || {
+                let mut state = PlayerState::new(player_id as u8);
+                for event in &events {
+                    if matches!(event, Event::Dahai { actor, .. } if *actor == player_id as u8) {
+                        let danger = state.calculate_danger().map(|d| d.tile_weights);
+                        if sender.send(danger).is_err() {
+                            return;
+                        }
+                    }
+                    if state.update(event).is_err() {
+                        return;
+                    }
+                }
+            });
+            receiver
+        });
+
+        let mut state = PlayerState::new(player_id as u8);
+        let mut riichi_discard_waits = [None; 34];
+        let mut tracker = KyokuTracker::default();
+        // own discard count when each tile type in tehai was last acquired while none of it was held
+        let mut held_since: [Option<u32>; 34] = [None; 34];
+        let debug = args.debug_player.as_deref() == Some(name.as_str());
+        let mut discard_rows: Vec<[String; 5]> = Vec::new();
+        let mut agari_streak = AgariStreak::default();
+
+        for event in &events {
+            let is_own_dahai = matches!(event, Event::Dahai { actor, .. } if *actor == player_id as u8);
+            let danger_before_event = if is_own_dahai && !args.skip_danger {
+                match &danger_receiver {
+                    // a failed update on the other thread fails the update below as well
+                    Some(receiver) => receiver
+                        .recv()
+                        .unwrap_or_else(|_| state.calculate_danger().map(|d| d.tile_weights)),
+                    // this is very slow and needs to be optimized
+                    None => state.calculate_danger().map(|d| d.tile_weights),
+                }
+            } else {
+                [[0.; 34]; 4]
+            };
+            // discards in riichi are forced and discards against a riichi are about defense
+            let pushing = !state.self_riichi_accepted() && !tracker.opponent_riichi.contains(&true);
+            let ukeire_before_event = (is_own_dahai && args.efficiency && pushing).then(|| discard_ukeire(&state));
+            // the hand before the draw, judged as a ron the same way as best_agari_points
+            let yakuless_on_ron = match event {
+                Event::Tsumo { actor, pai, .. } if *actor == player_id as u8 && state.waits[pai.deaka().as_usize()] => {
+                    matches!(state.calculate_agari(*pai, false, &[]), Ok(None))
+                }
+                _ => false,
+            };
+            state.update(event).map_err(|err| YilError::StateUpdate {
+                seat: player_id,
+                source: err.into(),
+            })?;
+            if duration.is_some() {
+                info.action_count += state.last_cans.can_act() as u32;
+            }
+            if let Event::Tsumo { actor, pai, .. } | Event::Dahai { actor, pai, .. } | Event::Kakan { actor, pai, .. } = event {
+                tracker.last_tile[*actor as usize] = Some(pai.deaka());
+            }
+            match event {
+                Event::Dahai { .. } => tracker.discard_count += 1,
+                Event::Chi { actor, .. }
+                | Event::Pon { actor, .. }
+                | Event::Daiminkan { actor, .. }
+                | Event::Kakan { actor, .. }
+                | Event::Ankan { actor, .. }
+                    if *actor == player_id as u8 =>
+                {
+                    tracker.own_tsumo_since_call = Some(0);
+                }
+                Event::Tsumo { actor, .. } => {
+                    tracker.tsumo_count += 1;
+                    if *actor == player_id as u8 {
+                        tracker.yakuless_on_ron = yakuless_on_ron;
+                        if let Some(own_tsumo) = &mut tracker.own_tsumo_since_riichi {
+                            *own_tsumo += 1;
+                        }
+                        if let Some(own_tsumo) = &mut tracker.own_tsumo_since_call {
+                            *own_tsumo += 1;
+                        }
+                    }
+                }
+                _ => {}
+            }
+            match event {
+                Event::StartKyoku {
+                    bakaze,
+                    kyoku,
+                    honba,
+                    oya,
+                    scores,
+                    ..
+                } => {
+                    info.kyoku_count += 1;
+                    info.total_haipai_shanten += state.shanten as u32;
+                    match state.shanten {
+                        ..=0 => info.haipai_shanten_0 += 1,
+                        1 => info.haipai_shanten_1 += 1,
+                        2 => info.haipai_shanten_2 += 1,
+                        3 => info.haipai_shanten_3 += 1,
+                        4 => info.haipai_shanten_4 += 1,
+                        5 => info.haipai_shanten_5 += 1,
+                        _ => info.haipai_shanten_6 += 1,
+                    }
+                    tracker = KyokuTracker {
+                        label: format!("{bakaze}{kyoku}-{honba}"),
+                        haipai_shanten: state.shanten as i32,
+                        self_tenpai_at: (state.shanten == 0).then_some(0),
+                        self_tenpai_turn: (state.shanten == 0).then_some(0),
+                        scores: *scores,
+                        ..Default::default()
+                    };
+                    let jikaze = must_tile!(27 + (player_id + 4 - *oya as usize) % 4);
+                    splits = kyoku_splits(args, *bakaze, jikaze);
+                    kyoku_label = format!("{bakaze}{kyoku}");
+                    held_since = [None; 34];
+                }
+                Event::Reach { actor } if *actor == player_id as u8 => {
+                    riichi_discard_waits = tenpai_discard_waits(&state);
+                }
+                Event::ReachAccepted { actor } if *actor != player_id as u8 => {
+                    tracker.opponent_tenpai_at = Some(tracker.discard_count);
+                    tracker.opponent_riichi[*actor as usize] = true;
+                    tracker.folding_against[*actor as usize] = (!state.self_riichi_declared() && !args.skip_danger).then_some(0);
+                    tracker.scores[*actor as usize] -= 1000;
+                    if !state.self_riichi_declared() && !tracker.valuable_tenpai_facing_riichi {
+                        tracker.valuable_tenpai_facing_riichi =
+                            best_agari_points(&state).is_some_and(|points| points >= args.overfold_min_points);
+                    }
+                }
+                Event::ReachAccepted { actor } if *actor == player_id as u8 => {
+                    let riichi_wait = state
+                        .waits
+                        .iter()
+                        .enumerate()
+                        .filter(|&(_, &is_wait)| is_wait)
+                        .map(|(tile, _)| 4 - state.tiles_seen[tile] as u32)
+                        .sum::<u32>();
+                    // standing before paying our own deposit
+                    let scores = tracker.scores;
+                    let rank = (0..4)
+                        .filter(|&seat| {
+                            scores[seat] > scores[player_id] || (scores[seat] == scores[player_id] && seat < player_id)
+                        })
+                        .count();
+                    match rank {
+                        0 => info.riichi_while_leading_count += 1,
+                        1 => info.riichi_while_second_count += 1,
+                        2 => info.riichi_while_third_count += 1,
+                        _ => info.riichi_while_last_count += 1,
+                    }
+                    // a config file can set a late_game_from that the command line would reject
+                    let late_game = kyoku_order(&args.late_game_from)
+                        .is_some_and(|late_game_from| kyoku_order(&kyoku_label).is_some_and(|kyoku| kyoku >= late_game_from));
+                    if rank == 0 && late_game {
+                        info.risky_lead_riichi_count += 1;
+                    }
+                    tracker.scores[player_id] -= 1000;
+                    // Hora deltas give the sticks to the winner, so the deposit has to leave ours for the sums to add up
+                    tracker.self_delta -= 1000;
+                    info.riichi_count += 1;
+                    info.total_riichi_wait += riichi_wait;
+                    tracker.own_tsumo_since_riichi = Some(0);
+                    if tracker.opponent_riichi.contains(&true) {
+                        info.chase_riichi_count += 1;
+                        tracker.chased_seats = tracker.opponent_riichi;
+                    }
+                    if riichi_discard_waits.iter().flatten().any(|&wait| wait > riichi_wait) {
+                        info.locked_bad_wait_count += 1;
+                    }
+                    if args.efficiency && riichi_discard_waits.iter().flatten().any(|&wait| wait < riichi_wait) {
+                        info.wait_widening_riichi_count += 1;
+                    }
+                }
+                Event::Dahai { actor, pai, tsumogiri } if *actor == player_id as u8 => {
+                    let turn = state.kawa[0].len() as u32 - 1;
+                    tracker.last_dahai_held_turns = held_since[pai.deaka().as_usize()].map_or(0, |since| turn - since);
+                    tracker.last_dahai_tsumogiri = *tsumogiri;
+                    tracker.last_ronnable_tile = Some(pai.deaka());
+                    if pai.is_aka() {
+                        info.wasted_aka_count += 1;
+                    }
+                    if !args.skip_danger {
+                        for seat in (0..4).filter(|&seat| seat != player_id) {
+                            let danger = danger_before_event[state.rel(seat as u8)];
+                            let tile_bit = 1 << pai.deaka().as_usize();
+                            tracker.last_dahai_stale_safe[seat] =
+                                danger[pai.deaka().as_usize()] > 0. && tracker.ever_safe_against[seat] & tile_bit != 0;
+                            tracker.last_dahai_had_safe[seat] = state
+                                .tehai
+                                .iter()
+                                .zip(danger)
+                                .any(|(&count, tile_danger)| count > 0 && tile_danger == 0.);
+                            for (tile, &tile_danger) in danger.iter().enumerate() {
+                                if tile_danger == 0. {
+                                    tracker.ever_safe_against[seat] |= 1 << tile;
+                                }
+                            }
+                        }
+                    }
+                    for (seat, discarded) in tracker.discarded_since_riichi.iter_mut().enumerate() {
+                        if tracker.opponent_riichi[seat] {
+                            *discarded |= 1 << pai.deaka().as_usize();
+                        }
+                    }
+                    if tracker.self_tenpai_at.is_none() && state.real_time_shanten() == 0 {
+                        tracker.self_tenpai_at = Some(tracker.discard_count);
+                        tracker.self_tenpai_turn = Some(state.kawa[0].len() as u32);
+                    }
+                    info.total_dahai_count += 1;
+                    match best_agari_points(&state) {
+                        Some(points) => tracker.tenpai_value = tracker.tenpai_value.max(Some(points)),
+                        None if state.real_time_shanten() > 0 => {
+                            tracker.left_tenpai_value = tracker.left_tenpai_value.max(tracker.tenpai_value.take());
+                        }
+                        // a yakuless tenpai keeps the value of the last one with yaku
+                        None => {}
+                    }
+                    if args.discard_matrix.is_some() {
+                        let discards = stats.discard_matrix.entry(name.clone()).or_insert_with(|| vec![0; 34]);
+                        discards[pai.deaka().as_usize()] += 1;
+                    }
+                    if let Some(ukeire) = ukeire_before_event {
+                        let best_shanten = ukeire.iter().flatten().map(|&(shanten, _)| shanten).min();
+                        let best_ukeire = ukeire
+                            .iter()
+                            .flatten()
+                            .filter(|&&(shanten, _)| Some(shanten) == best_shanten)
+                            .map(|&(_, ukeire)| ukeire)
+                            .max()
+                            .unwrap_or(0);
+                        if let Some((shanten, actual_ukeire)) = ukeire[pai.deaka().as_usize()] {
+                            if Some(shanten) == best_shanten {
+                                info.total_ukeire_loss += best_ukeire - actual_ukeire;
+                                info.efficiency_dahai_count += 1;
+                            }
+                        }
+                    }
+                    let riichi_dangers = (1..4)
+                        .filter(|&rel| state.riichi_declared[rel])
+                        .map(|rel| danger_before_event[rel][pai.deaka().as_usize()])
+                        .collect::<Vec<_>>();
+                    let is_genbutsu = !riichi_dangers.is_empty() && riichi_dangers.iter().all(|&danger| danger == 0.);
+                    // without the danger every tile looks genbutsu
+                    if is_genbutsu && !args.skip_danger {
+                        info.genbutsu_dahai_count += 1;
+                    }
+                    if !riichi_dangers.is_empty() && !is_genbutsu && !state.self_riichi_accepted() {
+                        info.push_dahai_count += 1;
+                        info.total_safe_tiles_at_push += state
+                            .tehai
+                            .iter()
+                            .enumerate()
+                            .filter(|&(tile, _)| {
+                                (1..4)
+                                    .filter(|&rel| state.riichi_declared[rel])
+                                    .all(|rel| danger_before_event[rel][tile] == 0.)
+                            })
+                            .map(|(_, &count)| count as u32)
+                            .sum::<u32>();
+                    }
+                    if tracker.valuable_tenpai_facing_riichi && !args.skip_danger {
+                        tracker.pushed_after_riichi |= !is_genbutsu;
+                        tracker.broke_tenpai_after_riichi |= state.real_time_shanten() > 0;
+                    }
+                    for (seat, folding) in tracker.folding_against.iter_mut().enumerate() {
+                        let genbutsu = danger_before_event[state.rel(seat as u8)][pai.deaka().as_usize()] == 0.;
+                        *folding = folding
+                            .filter(|_| genbutsu && !state.self_riichi_declared())
+                            .map(|count| count + 1);
+                    }
+                    for (player_kawa, player_danger) in state.kawa.iter().zip(danger_before_event).skip(1) {
+                        let is_ippatsu = player_kawa
+                            .last()
+                            .is_some_and(|item| item.as_ref().is_some_and(|item| item.sutehai.is_riichi));
+                        if is_ippatsu && !state.self_riichi_accepted() && player_danger[pai.deaka().as_usize()] > 0. {
+                            info.ippatsu_brazen_count += 1;
+                        }
+                    }
+                    if debug && args.file.is_some() {
+                        let danger = |rel: usize| {
+                            if state.riichi_declared[rel] {
+                                danger_before_event[rel][pai.deaka().as_usize()].to_string()
+                            } else {
+                                String::new()
+                            }
+                        };
+                        discard_rows.push([
+                            state.kawa[0].len().to_string(),
+                            pai.to_string(),
+                            danger(1),
+                            danger(2),
+                            danger(3),
+                        ]);
+                    }
+                }
+                Event::Chi { actor, .. } if *actor == player_id as u8 => {
+                    info.chi_from_kamicha += 1;
+                }
+                Event::Kakan { actor, pai, .. } if *actor == player_id as u8 => {
+                    tracker.last_ronnable_tile = Some(pai.deaka());
+                    // a robbed kan is not the discard the danger was judged for
+                    tracker.last_dahai_stale_safe = [false; 4];
+                    tracker.last_dahai_had_safe = [false; 4];
+                }
+                Event::Pon { actor, target, .. } if *actor == player_id as u8 => match state.rel(*target) {
+                    1 => info.pon_from_shimocha += 1,
+                    2 => info.pon_from_toimen += 1,
+                    _ => info.pon_from_kamicha += 1,
+                },
+                Event::Hora {
+                    actor,
+                    target,
+                    deltas,
+                    ura_markers,
+                } => {
+                    let Some(deltas) = deltas else {
+                        return Err(YilError::InconsistentLog("Hora without deltas".to_owned()));
+                    };
+                    tracker.self_delta += deltas[player_id];
+                    if actor == target && tracker.chased_seats[*actor as usize] {
+                        tracker.chased_riichi_tsumo = true;
+                    }
+
+                    // pao moves the payment onto the liable player, so a tsumo is paid by a single player and a ron is
+                    // split between the target and the liable player
+                    let mut payers = (0..4).filter(|&seat| seat != *actor as usize && deltas[seat] < 0);
+                    let liable_seat = if actor == target {
+                        let payer = payers.next();
+                        payer.filter(|_| payers.next().is_none())
+                    } else {
+                        payers.find(|&seat| seat != *target as usize)
+                    };
+                    if liable_seat == Some(player_id) {
+                        info.pao_liability_count += 1;
+                    } else if liable_seat.is_some() && *actor == player_id as u8 {
+                        info.pao_benefit_count += 1;
+                    }
+
+                    let mut normalized_self_delta = deltas[player_id] - state.honba as i32 * 300 - state.kyotaku as i32 * 1000;
+                    if state.is_oya() {
+                        normalized_self_delta = normalized_self_delta * 2 / 3;
+                    }
+
+                    if *actor == player_id as u8 {
+                        tracker.won = true;
+                        info.agari_count += 1;
+                        let agari_points = deltas[player_id] - state.honba as i32 * 300 - state.kyotaku as i32 * 1000;
+                        if tracker.left_tenpai_value.is_some_and(|points| agari_points > points as i32) {
+                            info.value_upgrade_agari_count += 1;
+                        }
+                        if actor == target && tracker.own_tsumo_since_riichi == Some(1) {
+                            info.riichi_declare_turn_tsumo += 1;
+                        }
+                        if actor == target && tracker.yakuless_on_ron {
+                            info.tsumo_only_agari_count += 1;
+                        }
+                        let winning_tile = tracker.last_tile[*target as usize].map(|tile| tile.as_usize());
+                        if let Some(winning_tile) = winning_tile.filter(|&tile| actor != target && tile < 27) {
+                            let near_called_tile = state.fuuro_overview[0]
+                                .iter()
+                                .filter(|meld| meld.len() == 3)
+                                .flat_map(|meld| meld.iter())
+                                .map(|tile| tile.deaka().as_usize())
+                                .any(|tile| tile / 9 == winning_tile / 9 && tile.abs_diff(winning_tile) <= 1);
+                            if near_called_tile {
+                                info.ron_on_called_shape_count += 1;
+                            }
+                        }
+                        let score_before = tracker.scores[player_id];
+                        if score_before < 0 && score_before + deltas[player_id] >= 0 {
+                            info.recovery_agari_count += 1;
+                        }
+                        if tracker
+                            .own_tsumo_since_call
+                            .is_some_and(|own_tsumo| own_tsumo <= (actor == target) as u32)
+                        {
+                            info.call_then_agari_count += 1;
+                        }
+                        match state.fuuro_overview[0].len() {
+                            0 => info.agari_by_melds_0 += 1,
+                            1 => info.agari_by_melds_1 += 1,
+                            2 => info.agari_by_melds_2 += 1,
+                            _ => info.agari_by_melds_3plus += 1,
+                        }
+                        match state.kawa[0].len() {
+                            0..=6 => info.agari_turn_1_6 += 1,
+                            7..=9 => info.agari_turn_7_9 += 1,
+                            10..=12 => info.agari_turn_10_12 += 1,
+                            _ => info.agari_turn_13plus += 1,
+                        }
+                        if let (Some(own), Some(opponent)) = (tracker.self_tenpai_at, tracker.opponent_tenpai_at) {
+                            if own > opponent {
+                                info.slow_tenpai_win_count += 1;
+                            }
+                        }
+                        info.total_agari_score += deltas[player_id] as u32;
+                        if args.dora_value.is_some() {
+                            // the tenhou details already counted this win's dora into its hora info
+                            let dora_count = hora_infos[kyoku_index][player_id].total_dora_in_wins;
+                            let bucket = if dora_count >= 4 {
+                                "4+".to_owned()
+                            } else {
+                                dora_count.to_string()
+                            };
+                            let cell = stats.dora_value.entry(name.clone()).or_default().entry(bucket).or_default();
+                            cell[0] += 1;
+                            cell[1] += deltas[player_id] as u32;
+                        }
+                        if state.is_menzen {
+                            if state.self_riichi_declared() {
+                                info.riichi_agari_count += 1;
+                                let ura_points = agari_points as f64 * (1.0 - ura_free_shares[kyoku_index][player_id]);
+                                info.riichi_agari_score += deltas[player_id] as u32;
+                                info.riichi_agari_score_excl_ura += (deltas[player_id] - ura_points.round() as i32) as u32;
+                            } else {
+                                info.dama_agari_count += 1;
+                                if actor == target {
+                                    info.dama_tsumo_count += 1;
+                                } else {
+                                    info.dama_ron_count += 1;
+                                }
+                            }
+                        } else {
+                            info.open_agari_count += 1;
+                        }
+                        if let Some(ura_markers) = ura_markers {
+                            let ura_count = state
+                                .tehai
+                                .iter()
+                                .enumerate()
+                                .map(|(tile, count)| {
+                                    if ura_markers.contains(&must_tile!(tile).next()) {
+                                        *count
+                                    } else {
+                                        0
+                                    }
+                                })
+                                .sum::<u8>();
+                            info.ura_count += ura_count as u32;
+                        }
+
+                        if normalized_self_delta >= 32000 {
+                            info.yakuman_count += 1;
+                        }
+                        if normalized_self_delta >= 24000 {
+                            info.sanbaiman_count += 1;
+                        }
+                        if normalized_self_delta >= 16000 {
+                            info.baiman_count += 1;
+                        }
+                        info.total_agari_waits += 1 + state
+                            .waits
+                            .iter()
+                            .enumerate()
+                            .filter(|&(_, &is_wait)| is_wait)
+                            .map(|(tile, _)| 4 - state.tiles_seen[tile] as u32)
+                            .sum::<u32>();
+                    } else if *target == player_id as u8 {
+                        tracker.dealt_in = true;
+                        info.dealin_count += 1;
+                        if tracker.last_dahai_stale_safe[*actor as usize] {
+                            info.stale_safe_dealin_count += 1;
+                        }
+                        if tracker.last_dahai_had_safe[*actor as usize] {
+                            info.avoidable_dealin_count += 1;
+                        }
+                        match tracker.last_ronnable_tile.map(|tile| tile.as_usize()) {
+                            Some(27..) => info.dealin_honor_count += 1,
+                            Some(tile) if tile % 9 == 0 || tile % 9 == 8 => info.dealin_terminal_count += 1,
+                            Some(_) => info.dealin_middle_count += 1,
+                            None => {}
+                        }
+                        match state.kawa[0].len() {
+                            0..=6 => info.dealin_turn_1_6 += 1,
+                            7..=9 => info.dealin_turn_7_9 += 1,
+                            10..=12 => info.dealin_turn_10_12 += 1,
+                            _ => info.dealin_turn_13plus += 1,
+                        }
+                        if state.real_time_shanten() == 0 {
+                            info.tenpai_dealin_count += 1;
+                        }
+                        if tracker.tsumo_count >= LIVE_WALL_DRAWS {
+                            info.houtei_dealin_count += 1;
+                        }
+                        if !tracker.last_dahai_tsumogiri && tracker.last_dahai_held_turns >= 2 {
+                            info.held_winning_tile_count += 1;
+                        }
+                        info.total_dealin_score += (-deltas[player_id]) as u32;
+                        let is_ippatsu = state.kawa[*actor as usize]
+                            .last()
+                            .is_some_and(|item| item.as_ref().is_some_and(|item| item.sutehai.is_riichi));
+                        if is_ippatsu && !state.self_riichi_accepted() {
+                            info.ippatsu_dealin_count += 1;
+                        }
+                        let last_discard_tedashi = state.kawa[state.rel(*actor)]
+                            .last()
+                            .is_some_and(|item| item.as_ref().is_some_and(|item| item.sutehai.is_tedashi));
+                        if last_discard_tedashi {
+                            info.tedashi_then_dealin_count += 1;
+                        }
+                        if !state.riichi_declared[state.rel(*actor)] && state.fuuro_overview[state.rel(*actor)].is_empty() {
+                            info.dama_dealin_count += 1;
+                            if normalized_self_delta <= -8000 {
+                                info.dama_mangan_dealin_count += 1;
+                            }
+                        }
+                    } else {
+                        tracker.other_hora = true;
+                        tracker.other_tsumo |= actor == target;
+                        let actor = *actor as usize;
+                        // a tsumo wins on the winner's own draw, a ron on the target's discard or added kan
+                        let winning_tile = tracker.last_tile[*target as usize];
+                        if let Some(winning_tile) = winning_tile.filter(|_| tracker.opponent_riichi[actor]) {
+                            let bit = 1 << winning_tile.as_usize();
+                            if tracker.held_since_riichi[actor] & bit != 0 && tracker.discarded_since_riichi[actor] & bit == 0 {
+                                info.withheld_riichi_winning_tile_count += 1;
+                            }
+                        }
+                    }
+                }
+                Event::Ryukyoku { deltas } => {
+                    tracker.drawn = true;
+                    if let Some(deltas) = deltas {
+                        tracker.self_delta += deltas[player_id];
+                    }
+                }
+                Event::Dora { .. } => {
+                    info.total_dora_indicators_seen += 1;
+                }
+                Event::EndKyoku => {
+                    if debug {
+                        tracker.trace(&name, state.self_riichi_declared());
+                    }
+                    debug!(
+                        player = %name,
+                        kyoku = %tracker.label,
+                        delta = tracker.self_delta,
+                        won = tracker.won,
+                        dealt_in = tracker.dealt_in,
+                        "kyoku ended"
+                    );
+                    if !state.is_menzen {
+                        info.open_count += 1;
+                        if !tracker.won && state.real_time_shanten() == 0 && best_agari_points(&state).is_none() {
+                            info.yakuless_open_count += 1;
+                        }
+                    }
+                    // abortive draws end before the live wall runs out
+                    let exhaustive_draw = tracker.drawn && tracker.tsumo_count >= LIVE_WALL_DRAWS;
+                    if tracker.self_tenpai_turn.is_some_and(|turn| turn <= 6) {
+                        info.early_tenpai_count += 1;
+                    }
+                    if exhaustive_draw && tracker.self_tenpai_at.is_some() && state.real_time_shanten() > 0 {
+                        info.folded_tenpai_count += 1;
+                    }
+                    let opponent_won = !tracker.won && (tracker.dealt_in || tracker.other_hora);
+                    if opponent_won && state.kawa[0].len() <= 2 {
+                        info.instant_loss_count += 1;
+                    }
+                    if state.self_riichi_declared() && !tracker.won && (tracker.dealt_in || tracker.other_hora) {
+                        info.riichi_then_loss_count += 1;
+                    }
+                    if tracker.chased_seats.contains(&true) {
+                        if tracker.won {
+                            info.chase_riichi_agari_count += 1;
+                        } else if tracker.dealt_in || tracker.chased_riichi_tsumo {
+                            info.chase_riichi_dealin_count += 1;
+                        }
+                    }
+                    if best_agari_points(&state).is_some_and(|points| points >= 32000) {
+                        info.yakuman_chance += 1;
+                    }
+                    if tracker.opponent_riichi.contains(&true) {
+                        info.faced_riichi_count += 1;
+                        // a won kyoku is survived too, only paying for an opponent win counts against it
+                        if !tracker.dealt_in && !tracker.other_tsumo {
+                            info.survived_riichi_count += 1;
+                        }
+                    }
+                    let folded_tenpai = tracker.broke_tenpai_after_riichi && !tracker.pushed_after_riichi;
+                    if tracker.valuable_tenpai_facing_riichi && folded_tenpai {
+                        info.overfold_count += 1;
+                    }
+
+                    for (seat, folding) in tracker.folding_against.iter().enumerate() {
+                        if folding.is_some_and(|count| count > 0) {
+                            induced_folds[kyoku_index][seat] += 1;
+                        }
+                    }
+                    info.total_score_delta += tracker.self_delta as i64;
+                    info.total_score_delta_sq += tracker.self_delta as i64 * tracker.self_delta as i64;
+                    if state.is_oya() {
+                        info.oya_kyoku_count += 1;
+                        info.oya_agari_count += tracker.won as u32;
+                        info.oya_dealin_count += tracker.dealt_in as u32;
+                    } else {
+                        info.ko_kyoku_count += 1;
+                        info.ko_agari_count += tracker.won as u32;
+                        info.ko_dealin_count += tracker.dealt_in as u32;
+                    }
+                    agari_streak.push(tracker.won);
+                    if args.timeline.is_some() && args.focus_player.contains(&name) {
+                        stats.kyoku_deltas.entry(name.clone()).or_default().push(tracker.self_delta);
+                    }
+
+                    info += &hora_infos[kyoku_index][player_id];
+                    kyoku_infos.push((splits.clone(), std::mem::take(&mut info)));
+                    if args.kyoku_heatmap.is_some() {
+                        let cell = stats
+                            .kyoku_heatmap
+                            .entry(name.clone())
+                            .or_default()
+                            .entry(kyoku_label.clone())
+                            .or_default();
+                        cell[0] += tracker.won as u32;
+                        cell[1] += tracker.dealt_in as u32;
+                        cell[2] += 1;
+                    }
+                    kyoku_index += 1;
+                }
+                _ => {}
+            }
+
+            for (seat, held) in tracker.held_since_riichi.iter_mut().enumerate() {
+                if tracker.opponent_riichi[seat] {
+                    for (tile, &count) in state.tehai.iter().enumerate() {
+                        if count > 0 {
+                            *held |= 1 << tile;
+                        }
+                    }
+                }
+            }
+            let own_discards = state.kawa[0].len() as u32;
+            for (since, &count) in held_since.iter_mut().zip(&state.tehai) {
+                if count == 0 {
+                    *since = None;
+                } else if since.is_none() {
+                    *since = Some(own_discards);
+                }
+            }
+        }
+
+        if debug && args.file.is_some() {
+            let mut csv_writer = create_csv_writer(&args.discards_output, args)?;
+            csv_writer.write_record(["turn", "tile", "danger_shimocha", "danger_toimen", "danger_kamicha"])?;
+            for row in discard_rows {
+                csv_writer.write_record(&row)?;
+            }
+            csv_writer.flush()?;
+        }
+
+        // an afk or disconnected player sits on every timeout, so their game only says something about the clock
+        let game_actions: u32 = kyoku_infos.iter().map(|(_, info)| info.action_count).sum();
+        let timing_kept = match (duration, args.max_seconds_per_action) {
+            (Some(duration), Some(max_seconds)) => game_actions > 0 && duration as f64 / game_actions as f64 <= max_seconds,
+            (duration, None) => duration.is_some(),
+            (None, Some(_)) => false,
+        };
+        if let Some(duration) = duration.filter(|_| timing_kept) {
+            stats.players_info.entry(name.clone()).or_default().seconds_played += duration as u32;
+        }
+
+        stats.players_info.entry(name.clone()).or_default().max_agari_streak += Max(agari_streak.longest);
+        stats.agari_streaks.insert(name.clone(), agari_streak);
+        seat_kyoku_infos.push((player_id, name, timing_kept, kyoku_infos));
+    }
+
+    for (player_id, name, timing_kept, kyoku_infos) in seat_kyoku_infos {
+        for (kyoku_index, (splits, mut info)) in kyoku_infos.into_iter().enumerate() {
+            if !timing_kept {
+                info.action_count = 0;
+            }
+            info.riichi_induced_fold_estimate += induced_folds[kyoku_index][player_id];
+            *stats.players_info.entry(name.clone()).or_default() += &info;
+            for split in &splits {
+                *stats
+                    .split_info
+                    .entry(split.clone())
+                    .or_default()
+                    .entry(name.clone())
+                    .or_default() += &info;
+            }
+        }
+    }
+
+    Ok(Some(stats))
+}
+
+/// Every output goes through here so that headers and rows alike are quoted by the csv writer, player and yaku names can
+/// contain commas and quotes
+fn create_csv_writer(path: &Path, args: &Args) -> Result<Writer<std::fs::File>> {
+    let mut file = std::fs::File::create(path).map_err(YilError::io(path))?;
+    if args.excel {
+        // without a BOM excel reads the file in the local code page and garbles non-ascii names
+        file.write_all(b"\xEF\xBB\xBF").map_err(YilError::io(path))?;
+    }
+    Ok(WriterBuilder::new().quote_style(QuoteStyle::Necessary).from_writer(file))
+}
+
+/// Whether the name filters keep a player: --exclude-exact over --include-exact over --exclude
+fn name_included(name: &str, args: &Args) -> bool {
+    if args.exclude_exact.iter().any(|excluded| excluded == name) {
+        false
+    } else if args.include_exact.iter().any(|included| included == name) {
+        true
+    } else {
+        // every name contains the empty string, which only opts out of the default
+        !args
+            .exclude
+            .iter()
+            .any(|excluded| !excluded.is_empty() && name.contains(excluded.as_str()))
+    }
+}
+
+/// The players worth reporting, sorted by kyoku count
+fn included_entries(players_info: HashMap<String, PlayerInfo>, args: &Args) -> Vec<(String, PlayerInfo)> {
+    let mut entries: Vec<(String, PlayerInfo)> = players_info
+        .into_iter()
+        .filter(|(name, info)| info.kyoku_count >= args.min_kyoku && name_included(name, args))
+        .collect();
+    entries.sort_by(|(lname, l), (rname, r)| r.kyoku_count.cmp(&l.kyoku_count).then(lname.cmp(rname)));
+    entries
+}
+
+/// Writes the players in the given order with the derived columns after the counts, and last the columns that compare a
+/// player against everyone else in the file
+fn write_info(path: &Path, entries: &[(String, PlayerInfo)], levels: &PlayerLevels, args: &Args) -> Result<()> {
+    let mut csv_writer = create_csv_writer(path, args)?;
+
+    let base_headers = PlayerInfo::CSV_HEADERS.iter().copied();
+    let derived_headers = PlayerInfo::DERIVED_HEADERS.iter().copied();
+    let header: Vec<&str> = ["name", "level"]
+        .into_iter()
+        .chain(base_headers)
+        .chain(derived_headers)
+        .chain(["placement_percentile"])
+        .collect();
+    csv_writer.write_record(&header)?;
+
+    // players without a game with final scores have no placement to rank, their cell stays empty
+    let placed: Vec<&PlayerInfo> = entries
+        .iter()
+        .map(|(_, info)| info)
+        .filter(|info| info.placed_game_count > 0)
+        .collect();
+    for (name, info) in entries {
+        // share of the other placed players with a worse average placement, lower placements are better
+        let worse_count = placed
+            .iter()
+            .filter(|other| other.avg_placement() > info.avg_placement())
+            .count();
+        let placement_percentile = if info.placed_game_count == 0 {
+            String::new()
+        } else if placed.len() > 1 {
+            fmt_ratio(100.0 * worse_count as f64 / (placed.len() - 1) as f64, args.precision)
+        } else {
+            fmt_ratio(0.0, args.precision)
+        };
+        let row: Vec<String> = [name.clone(), level_label(levels, name)]
+            .into_iter()
+            .chain(info.to_csv_values())
+            .chain(info.derived_values(args.precision))
+            .chain([placement_percentile])
+            .collect();
+        csv_writer.write_record(&row)?;
+    }
+    csv_writer.flush()?;
+
+    Ok(())
+}
+
+/// One json object per included player on stdout, the same fields as info.csv without the derived columns
+fn write_ndjson(entries: &[(String, PlayerInfo)], levels: &PlayerLevels) -> Result<()> {
+    #[derive(Serialize)]
+    struct Row<'a> {
+        name: &'a str,
+        level: String,
+        #[serde(flatten)]
+        info: &'a PlayerInfo,
+    }
+
+    let mut stdout = std::io::stdout().lock();
+    for (name, info) in entries {
+        let level = level_label(levels, name);
+        serde_json::to_writer(&mut stdout, &Row { name, level, info })?;
+        writeln!(stdout).map_err(YilError::Stdout)?;
+    }
+    stdout.flush().map_err(YilError::Stdout)?;
+    Ok(())
+}
+
+impl Args {
+    fn manifest_path(&self) -> Option<PathBuf> {
+        self.manifest
+            .clone()
+            .or_else(|| self.file.is_none().then(|| self.log_dir.join(DEFAULT_MANIFEST)))
+    }
+
+    /// Key of the manifest, the options that change what a single log contributes. Output, logging and caching options
+    /// are left out so that changing them reuses the manifest, the aliases are in so that editing the file does not
+    fn manifest_settings(&self, yaku_aliases: &BTreeMap<String, String>) -> String {
+        #[derive(Debug)]
+        struct Settings<'a> {
+            focus_player: &'a [String],
+            self_only: &'a Option<String>,
+            min_game_seconds: Option<u64>,
+            keep_untimed: bool,
+            rated_only: bool,
+            level: &'a str,
+            tie_mode: TieMode,
+            split_round: bool,
+            by_seat_wind: bool,
+            skip_danger: bool,
+            efficiency: bool,
+            overfold_min_points: u32,
+            late_game_from: &'a str,
+            max_seconds_per_action: Option<f64>,
+            // only whether these outputs are written decides what is collected per log
+            kyoku_heatmap: bool,
+            dora_value: bool,
+            discard_matrix: bool,
+            timeline: bool,
+            yaku_aliases: &'a BTreeMap<String, String>,
+        }
+        format!(
+            "{:?}",
+            Settings {
+                focus_player: &self.focus_player,
+                self_only: &self.self_only,
+                min_game_seconds: self.min_game_seconds,
+                keep_untimed: self.keep_untimed,
+                rated_only: self.rated_only,
+                level: &self.level,
+                tie_mode: self.tie_mode,
+                split_round: self.split_round,
+                by_seat_wind: self.by_seat_wind,
+                skip_danger: self.skip_danger,
+                efficiency: self.efficiency,
+                overfold_min_points: self.overfold_min_points,
+                late_game_from: &self.late_game_from,
+                max_seconds_per_action: self.max_seconds_per_action,
+                kyoku_heatmap: self.kyoku_heatmap.is_some(),
+                dora_value: self.dora_value.is_some(),
+                discard_matrix: self.discard_matrix.is_some(),
+                timeline: self.timeline.is_some(),
+                yaku_aliases,
+            }
+        )
+    }
+
+    fn writes_ndjson(&self) -> bool {
+        self.ndjson || matches!(self.format, OutputFormat::Ndjson | OutputFormat::All)
+    }
+
+    fn writes_csv(&self) -> bool {
+        !self.ndjson && matches!(self.format, OutputFormat::Csv | OutputFormat::All)
+    }
+
+    /// Parses the command line and fills the options it did not give from the config file
+    pub fn parse_with_config() -> Result<Args> {
+        let matches = Args::command().get_matches();
+        let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+        let config_path = match &args.config {
+            Some(config_path) => config_path.clone(),
+            None if Path::new(DEFAULT_CONFIG).exists() => PathBuf::from(DEFAULT_CONFIG),
+            None => return Ok(args),
+        };
+        let toml_string = std::fs::read_to_string(&config_path).map_err(YilError::io(&config_path))?;
+        let config: Config = toml::from_str(&toml_string).map_err(|source| YilError::Config {
+            path: config_path.clone(),
+            source,
+        })?;
+        // the command line runs parse_fraction, a config value has to be checked here before it reaches gen_bool
+        if config.sample.is_some_and(|sample| !(0.0..=1.0).contains(&sample)) {
+            return Err(YilError::Config {
+                path: config_path,
+                source: serde::de::Error::custom("sample: expected a number from 0 to 1"),
+            });
+        }
+        config.apply(&mut args, &matches);
+        Ok(args)
+    }
+}
+
+/// Reads the players of an info csv back by column name with their levels, derived and unknown columns are ignored
+fn read_info(path: &Path) -> Result<Vec<(String, BTreeSet<String>, PlayerInfo)>> {
+    let mut csv_reader = csv::Reader::from_path(path)?;
+    let headers = csv_reader.headers()?.clone();
+    if !headers.iter().any(|header| header == "name") {
+        return Err(YilError::InfoCsv {
+            path: path.to_owned(),
+            message: "no name column".to_owned(),
+        });
+    }
+    let mut entries = Vec::new();
+    for record in csv_reader.records() {
+        let record = record?;
+        let mut name = String::new();
+        let mut levels = BTreeSet::new();
+        let mut fields = serde_json::Map::new();
+        for (header, value) in headers.iter().zip(record.iter()) {
+            if header == "name" {
+                name = value.to_owned();
+            } else if header == "level" {
+                levels = value
+                    .split("; ")
+                    .filter(|level| !level.is_empty())
+                    .map(str::to_owned)
+                    .collect();
+            } else if PlayerInfo::CSV_HEADERS.contains(&header) {
+                let value = serde_json::from_str(value).map_err(|_| YilError::InfoCsv {
+                    path: path.to_owned(),
+                    message: format!("{header} of {name} is not a number: {value:?}"),
+                })?;
+                fields.insert(header.to_owned(), value);
+            }
+        }
+        entries.push((name, levels, serde_json::from_value(serde_json::Value::Object(fields))?));
+    }
+    Ok(entries)
+}
+
+fn merge_info(inputs: &[PathBuf], output: &Path, args: &Args) -> Result<ExitCode> {
+    let mut players_info: HashMap<String, PlayerInfo> = HashMap::new();
+    let mut player_levels = PlayerLevels::new();
+    for input in inputs {
+        for (name, levels, info) in read_info(input)? {
+            player_levels.entry(name.clone()).or_default().extend(levels);
+            *players_info.entry(name).or_default() += &info;
+        }
+    }
+    let entries = included_entries(players_info, args);
+    write_info(output, &entries, &player_levels, args)?;
+    status!(args, "{} files merged, {} players written", inputs.len(), entries.len());
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Orders per-game rows by game start, logs come in directory order and untimed ones sort first
+fn sort_by_start_time<T>(rows: &mut [(Option<u64>, T)]) {
+    rows.sort_by_key(|(start_time, _)| *start_time);
+}
+
+/// --file or every entry of --log-dir kept by --sample, directories are left to the caller to skip. Hidden files such
+/// as the default manifest are not logs
+fn log_paths(args: &Args) -> Result<Vec<PathBuf>> {
+    let mut paths = match &args.file {
+        Some(file) => return Ok(vec![file.clone()]),
+        None => std::fs::read_dir(&args.log_dir)
+            .and_then(|entries| {
+                entries
+                    .map(|entry| entry.map(|entry| entry.path()))
+                    .collect::<std::io::Result<Vec<_>>>()
+            })
+            .map_err(YilError::io(&args.log_dir))?,
+    };
+    paths.retain(|path| {
+        !path
+            .file_name()
+            .is_some_and(|file_name| file_name.as_encoded_bytes().starts_with(b"."))
+    });
+    if let Some(fraction) = args.sample {
+        // one generator per file seeded from its name, so a file's draw doesn't depend on the files listed before it
+        paths.retain(|path| {
+            let file_name = path.file_name().unwrap_or_default().as_encoded_bytes();
+            StdRng::seed_from_u64(args.seed ^ fnv1a(file_name)).gen_bool(fraction)
+        });
+    }
+    Ok(paths)
+}
+
+/// Kyoku counts straight from the parsed tenhou logs, every kyoku of a log starts with one StartKyoku event
+fn list_players(args: &Args) -> Result<ExitCode> {
+    let mut kyoku_counts: HashMap<String, usize> = HashMap::new();
+    let mut errored_count = 0;
+    for path in log_paths(args)? {
+        if !path.is_file() {
+            continue;
+        }
+        let log = std::fs::read_to_string(&path)
+            .map_err(YilError::io(&path))
+            .and_then(|json_string| Log::from_json_str(&json_string).map_err(|err| YilError::TenhouParse(err.into())));
+        match log {
+            Ok(log) => {
+                for name in &log.names {
+                    *kyoku_counts.entry(name.clone()).or_default() += log.kyokus.len();
+                }
+            }
+            Err(err) => {
+                warn!(path = %path.display(), error = err.chain(), "failed to read, skipping");
+                errored_count += 1;
+            }
+        }
+    }
+    let mut entries: Vec<(String, usize)> = kyoku_counts.into_iter().collect();
+    entries.sort_by(|(lname, l), (rname, r)| r.cmp(l).then(lname.cmp(rname)));
+    let width = entries.iter().map(|(name, _)| name.chars().count()).max().unwrap_or(0);
+    for (name, kyoku_count) in entries {
+        status!(args, "{name:<width$}  {kyoku_count}");
+    }
+    Ok(if errored_count > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+fn export_kyoku(file: &Path, kyoku_index: usize, output: Option<&Path>, args: &Args) -> Result<ExitCode> {
+    let json_string = std::fs::read_to_string(file).map_err(YilError::io(file))?;
+    let log = Log::from_json_str(&json_string).map_err(|err| YilError::TenhouParse(err.into()))?;
+    let events = mjai_events(&log, &json_string, file, args.mjai_cache.as_deref())?;
+    let start_indices: Vec<usize> = events
+        .iter()
+        .enumerate()
+        .filter(|(_, event)| matches!(event, Event::StartKyoku { .. }))
+        .map(|(index, _)| index)
+        .collect();
+    let Some(&start) = start_indices.get(kyoku_index) else {
+        return Err(YilError::NoKyoku {
+            path: file.to_owned(),
+            index: kyoku_index,
+            count: start_indices.len(),
+        });
+    };
+    let end = events[start..]
+        .iter()
+        .position(|event| matches!(event, Event::EndKyoku))
+        .map_or(events.len(), |offset| start + offset + 1);
+    let start_game = events.first().filter(|event| matches!(event, Event::StartGame { .. }));
+
+    let mut contents = String::new();
+    for event in start_game.into_iter().chain(&events[start..end]) {
+        contents += &serde_json::to_string(event)?;
+        contents.push('\n');
+    }
+    match output {
+        Some(output) => std::fs::write(output, contents).map_err(YilError::io(output))?,
+        None => std::io::stdout()
+            .lock()
+            .write_all(contents.as_bytes())
+            .map_err(YilError::Stdout)?,
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Everything the binary does, parses the arguments from the command line
+pub fn run() -> Result<ExitCode> {
+    let args = Args::parse_with_config()?;
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::new(&args.log_level))
+        .with_writer(std::io::stderr)
+        .init();
+    if let Some(Command::Merge { inputs, output }) = &args.command {
+        return merge_info(inputs, output, &args);
+    }
+    if let Some(Command::List) = &args.command {
+        return list_players(&args);
+    }
+    if let Some(Command::ExportKyoku {
+        file,
+        kyoku_index,
+        output,
+    }) = &args.command
+    {
+        return export_kyoku(file, *kyoku_index, output.as_deref(), &args);
+    }
+
+    let yaku_aliases: BTreeMap<String, String> = match &args.yaku_aliases {
+        Some(aliases_path) => {
+            let toml_string = std::fs::read_to_string(aliases_path).map_err(YilError::io(aliases_path))?;
+            toml::from_str(&toml_string).map_err(|source| YilError::YakuAliases {
+                path: aliases_path.clone(),
+                source,
+            })?
+        }
+        None => BTreeMap::new(),
+    };
+
+    let settings = args.manifest_settings(&yaku_aliases);
+    let manifest_path = args.manifest_path();
+    let mut previous_manifest = match manifest_path.as_deref().filter(|_| !args.no_cache) {
+        Some(manifest_path) => Manifest::load(manifest_path, &settings)?,
+        None => Manifest::default(),
+    };
+    let mut manifest = Manifest {
+        settings,
+        files: HashMap::new(),
+    };
+
+    // single accumulator for every player across every log
+    let mut total = LogStats::default();
+    let mut monthly_info: BTreeMap<(String, String), PlayerInfo> = BTreeMap::new();
+    let mut session_streaks: Vec<(Option<u64>, HashMap<String, AgariStreak>)> = Vec::new();
+    let mut margin_rows: Vec<(Option<u64>, [String; 5])> = Vec::new();
+    let mut game_deltas: Vec<(Option<u64>, HashMap<String, Vec<i32>>)> = Vec::new();
+    let mut processed_count = 0;
+    let mut cached_count = 0;
+    let mut skipped_count = 0;
+    let mut errored_count = 0;
+
+    #[cfg(feature = "net")]
+    if let Some(urls_path) = &args.urls {
+        net::download_urls(urls_path, &args.log_dir)?;
+    }
+
+    for path in log_paths(&args)? {
+        if !path.is_file() {
+            continue;
+        }
+        let _log_span = tracing::info_span!("log", path = %path.display()).entered();
+        let metadata = std::fs::metadata(&path).map_err(YilError::io(&path))?;
+        let size = metadata.len();
+        let modified = metadata
+            .modified()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).map_err(std::io::Error::other))
+            .map_err(YilError::io(&path))?
+            .as_secs();
+        let key = path.to_string_lossy().into_owned();
+
+        // unchanged files reuse their previous contribution, new or grown files are reprocessed in full and
+        // replace it since the totals are always rebuilt from the per-file entries
+        let stats = match previous_manifest.files.remove(&key) {
+            Some(cached) if cached.size == size && cached.modified == modified => {
+                cached_count += 1;
+                cached.stats
+            }
+            _ => {
+                info!("processing");
+                match process_log(&path, &args, &yaku_aliases) {
+                    Ok(stats) => stats,
+                    Err(err) if !args.strict => {
+                        warn!(error = err.chain(), "failed to process, skipping");
+                        errored_count += 1;
+                        continue;
+                    }
+                    Err(err) => {
+                        return Err(YilError::Process {
+                            path,
+                            source: Box::new(err),
+                        })
+                    }
+                }
+            }
+        };
+        match &stats {
+            Some(stats) => {
+                processed_count += 1;
+                total.merge(stats);
+                if args.session {
+                    session_streaks.push((stats.start_time, stats.agari_streaks.clone()));
+                }
+                let standings = stats.final_standings.as_slice();
+                if let (Some(_), [(first, first_score), (_, second_score), (_, third_score), (last, last_score)]) =
+                    (&args.margins, standings)
+                {
+                    let row = [
+                        path.display().to_string(),
+                        first.clone(),
+                        last.clone(),
+                        (first_score - second_score).to_string(),
+                        (third_score - last_score).to_string(),
+                    ];
+                    margin_rows.push((stats.start_time, row));
+                }
+                if args.timeline.is_some() {
+                    game_deltas.push((stats.start_time, stats.kyoku_deltas.clone()));
+                }
+                if let Some(start_time) = stats.start_time.filter(|_| args.monthly.is_some()) {
+                    let month = year_month(start_time);
+                    for (name, info) in &stats.players_info {
+                        *monthly_info.entry((name.clone(), month.clone())).or_default() += info;
+                    }
+                }
+            }
+            None => skipped_count += 1,
+        }
+        if let Some(manifest_path) = &manifest_path {
+            manifest.files.insert(key, ManifestEntry { size, modified, stats });
+            // a config file can set 0, which the command line rejects
+            if args
+                .checkpoint_every
+                .is_some_and(|every| every > 0 && manifest.files.len() as u64 % every == 0)
+            {
+                manifest.save_checkpoint(manifest_path, &previous_manifest)?;
+            }
+        }
+    }
+    if let Some(manifest_path) = &manifest_path {
+        manifest.save(manifest_path)?;
+    }
+    if args.session {
+        sort_by_start_time(&mut session_streaks);
+        let mut joined_streaks: HashMap<String, AgariStreak> = HashMap::new();
+        for (_, agari_streaks) in &session_streaks {
+            for (name, streak) in agari_streaks {
+                joined_streaks
+                    .entry(name.clone())
+                    .and_modify(|joined| *joined = joined.then(streak))
+                    .or_insert(*streak);
+            }
+        }
+        for (name, streak) in joined_streaks {
+            total.players_info.entry(name).or_default().max_agari_streak = Max(streak.longest);
+        }
+    }
+    let LogStats {
+        players_info,
+        yaku_info,
+        split_info,
+        kyoku_heatmap,
+        dora_value,
+        discard_matrix,
+        levels,
+        ..
+    } = total;
+
+    // player info
+    // compared players do not have to pass the filters of the outputs
+    let compared = args.compare.as_ref().map(|(left, right)| {
+        let info = |name: &String| players_info.get(name).cloned().unwrap_or_default();
+        [(left.clone(), info(left)), (right.clone(), info(right))]
+    });
+    let player_entries = included_entries(players_info, &args);
+    if args.writes_ndjson() {
+        write_ndjson(&player_entries, &levels)?;
+    }
+    if args.writes_csv() {
+        write_info(&args.info_output, &player_entries, &levels, &args)?;
+    }
+    let name_order = player_entries.iter().map(|(name, _)| name.clone()).collect::<Vec<_>>();
+    for (split, players_info) in split_info {
+        let stem = args.info_output.file_stem().unwrap_or_default().to_string_lossy();
+        let split_path = args.info_output.with_file_name(format!("{stem}_{split}.csv"));
+        write_info(&split_path, &included_entries(players_info, &args), &levels, &args)?;
+    }
+
+    if let Some(heatmap_path) = &args.kyoku_heatmap {
+        let mut csv_writer = create_csv_writer(heatmap_path, &args)?;
+        csv_writer.write_record(["name", "kyoku", "agari_count", "dealin_count", "kyoku_count"])?;
+        for name in &name_order {
+            let Some(kyoku_cells) = kyoku_heatmap.get(name) else {
+                continue;
+            };
+            // round wind order first, the labels alone would sort north before south
+            let mut kyoku_cells: Vec<_> = kyoku_cells.iter().collect();
+            kyoku_cells.sort_by_key(|(kyoku_label, _)| ("ESWN".find(&kyoku_label[..1]), kyoku_label[1..].to_owned()));
+            for (kyoku_label, [agari_count, dealin_count, kyoku_count]) in kyoku_cells {
+                let row = [
+                    name.clone(),
+                    kyoku_label.clone(),
+                    agari_count.to_string(),
+                    dealin_count.to_string(),
+                    kyoku_count.to_string(),
+                ];
+                csv_writer.write_record(&row)?;
+            }
+        }
+        csv_writer.flush()?;
+    }
+
+    if let Some(dora_value_path) = &args.dora_value {
+        let mut csv_writer = create_csv_writer(dora_value_path, &args)?;
+        csv_writer.write_record(["name", "dora_count", "agari_count", "total_agari_score"])?;
+        for name in &name_order {
+            for (bucket, [agari_count, total_agari_score]) in dora_value.get(name).into_iter().flatten() {
+                csv_writer.write_record([name, bucket, &agari_count.to_string(), &total_agari_score.to_string()])?;
+            }
+        }
+        csv_writer.flush()?;
+    }
+
+    if let Some(margins_path) = &args.margins {
+        sort_by_start_time(&mut margin_rows);
+        let mut csv_writer = create_csv_writer(margins_path, &args)?;
+        csv_writer.write_record(["log", "first", "last", "first_second_margin", "third_last_margin"])?;
+        for (_, row) in &margin_rows {
+            csv_writer.write_record(row)?;
+        }
+        csv_writer.flush()?;
+    }
+
+    if let Some(timeline_path) = &args.timeline {
+        sort_by_start_time(&mut game_deltas);
+        let mut csv_writer = create_csv_writer(timeline_path, &args)?;
+        csv_writer.write_record(["player", "game_timestamp", "kyoku_index", "delta", "running_total"])?;
+        let mut running_totals = vec![0; args.focus_player.len()];
+        for (start_time, player_deltas) in &game_deltas {
+            let game_timestamp = start_time.map(|start_time| start_time.to_string()).unwrap_or_default();
+            // every focus player keeps their own running total, rows follow the order the names were given in
+            let focus_deltas = args.focus_player.iter().zip(&mut running_totals);
+            for (focus_player, running_total) in focus_deltas {
+                let Some(kyoku_deltas) = player_deltas.get(focus_player) else {
+                    continue;
+                };
+                for (kyoku_index, delta) in kyoku_deltas.iter().enumerate() {
+                    *running_total += delta;
+                    let row = [
+                        focus_player.clone(),
+                        game_timestamp.clone(),
+                        kyoku_index.to_string(),
+                        delta.to_string(),
+                        running_total.to_string(),
+                    ];
+                    csv_writer.write_record(&row)?;
+                }
+            }
+        }
+        csv_writer.flush()?;
+    }
+
+    if let Some(discard_matrix_path) = &args.discard_matrix {
+        let mut csv_writer = create_csv_writer(discard_matrix_path, &args)?;
+        let header = std::iter::once("name".to_owned()).chain((0..34_usize).map(|tile| must_tile!(tile).to_string()));
+        csv_writer.write_record(header)?;
+        for name in &name_order {
+            let Some(discards) = discard_matrix.get(name) else {
+                continue;
+            };
+            let row = std::iter::once(name.clone()).chain(discards.iter().map(|count| count.to_string()));
+            csv_writer.write_record(row)?;
+        }
+        csv_writer.flush()?;
+    }
+
+    if let Some(monthly_path) = &args.monthly {
+        let mut csv_writer = create_csv_writer(monthly_path, &args)?;
+        let header: Vec<&str> = ["name", "month"]
+            .into_iter()
+            .chain(PlayerInfo::CSV_HEADERS.iter().copied())
+            .chain(PlayerInfo::DERIVED_HEADERS.iter().copied())
+            .collect();
+        csv_writer.write_record(&header)?;
+        for ((name, month), info) in monthly_info {
+            if !name_order.contains(&name) {
+                continue;
+            }
+            let row: Vec<String> = [name, month]
+                .into_iter()
+                .chain(info.to_csv_values())
+                .chain(info.derived_values(args.precision))
+                .collect();
+            csv_writer.write_record(&row)?;
+        }
+        csv_writer.flush()?;
+    }
+
+    // yaku info
+    let mut csv_writer = create_csv_writer(&args.yaku_output, &args)?;
+
+    let mut total_yaku_counts: HashMap<String, u32> = HashMap::new();
+    for inner_map in yaku_info.values() {
+        for (key, value) in inner_map {
+            *total_yaku_counts.entry(key.clone()).or_insert(0) += value;
+        }
+    }
+    let mut yaku_order: Vec<(String, u32)> = total_yaku_counts.into_iter().collect();
+    yaku_order.sort_by(|(_, l), (_, r)| r.cmp(l));
+
+    let header = std::iter::once("name")
+        .chain(yaku_order.iter().map(|(y, _)| y.as_str()))
+        .collect::<Vec<_>>();
+    csv_writer.write_record(header)?;
+
+    if let Some(missing_yaku_path) = &args.missing_yaku {
+        let mut missing_writer = create_csv_writer(missing_yaku_path, &args)?;
+        missing_writer.write_record(["name", "missing_yaku"])?;
+        for name in &name_order {
+            let player_yaku = yaku_info.get(name);
+            let missing_yaku = yaku_order
+                .iter()
+                .filter(|(yaku, _)| player_yaku.and_then(|player_yaku| player_yaku.get(yaku)).is_none())
+                .map(|(yaku, _)| yaku.as_str())
+                .collect::<Vec<_>>();
+            missing_writer.write_record([name.as_str(), &missing_yaku.join("; ")])?;
+        }
+        missing_writer.flush()?;
+    }
+
+    let mut entries: Vec<(String, HashMap<String, u32>)> =
+        yaku_info.into_iter().filter(|(name, _)| name_order.contains(name)).collect();
+    entries.sort_by_key(|(name, _)| name_order.iter().position(|n| n == name));
+
+    for (name, info) in entries {
+        let entries = yaku_order
+            .iter()
+            .map(|(yaku, _)| info.get(yaku).cloned().unwrap_or(0).to_string());
+        let row: Vec<String> = std::iter::once(name).chain(entries).collect();
+        csv_writer.write_record(row)?;
+    }
+    csv_writer.flush()?;
+
+    status!(
+        args,
+        "{processed_count} logs processed ({cached_count} from manifest), {skipped_count} skipped, {errored_count} errored, \
+         {} players written",
+        name_order.len()
+    );
+    let metrics: [(&str, fn(&PlayerInfo) -> f64); 3] = [
+        ("agari_rate", PlayerInfo::agari_rate),
+        ("dealin_rate", PlayerInfo::dealin_rate),
+        ("avg_placement", PlayerInfo::avg_placement),
+    ];
+    for (metric, value) in metrics {
+        // avg_placement is 0 without placed games, which would pull its median below every real placement
+        let mut values: Vec<f64> = player_entries
+            .iter()
+            .filter(|(_, info)| metric != "avg_placement" || info.placed_game_count > 0)
+            .map(|(_, info)| value(info))
+            .collect();
+        values.sort_by(f64::total_cmp);
+        if let (Some(median), Some(p90)) = (percentile(&values, 0.5), percentile(&values, 0.9)) {
+            status!(
+                args,
+                "{metric}: median {}, p90 {}",
+                fmt_ratio(median, args.precision),
+                fmt_ratio(p90, args.precision)
+            );
+        }
+    }
+    if let Some(compared) = &compared {
+        print_comparison(compared, &args);
+    }
+    Ok(if errored_count > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+/// Aligned table of every info.csv column of two players and the first minus the second
+fn print_comparison([(left_name, left), (right_name, right)]: &[(String, PlayerInfo); 2], args: &Args) {
+    let headers = PlayerInfo::CSV_HEADERS.iter().chain(PlayerInfo::DERIVED_HEADERS);
+    let left_values = left.to_csv_values().into_iter().chain(left.derived_values(args.precision));
+    let right_values = right.to_csv_values().into_iter().chain(right.derived_values(args.precision));
+    let rows: Vec<[String; 4]> = headers
+        .zip(left_values.zip(right_values))
+        .map(|(header, (left_value, right_value))| {
+            let delta = match (left_value.parse::<f64>(), right_value.parse::<f64>()) {
+                (Ok(left_number), Ok(right_number)) => fmt_ratio(left_number - right_number, args.precision),
+                _ => String::new(),
+            };
+            [header.to_string(), left_value, right_value, delta]
+        })
+        .collect();
+    let header_row = ["stat".to_owned(), left_name.clone(), right_name.clone(), "delta".to_owned()];
+    let widths: [usize; 4] = std::array::from_fn(|column| {
+        rows.iter()
+            .chain([&header_row])
+            .map(|row| row[column].chars().count())
+            .max()
+            .unwrap_or(0)
+    });
+    for row in [&header_row].into_iter().chain(&rows) {
+        status!(
+            args,
+            "{:<w0$}  {:>w1$}  {:>w2$}  {:>w3$}",
+            row[0],
+            row[1],
+            row[2],
+            row[3],
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2],
+            w3 = widths[3]
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(extra_args: &[&str]) -> Args {
+        Args::parse_from(["tournament_review"].iter().chain(extra_args))
+    }
+
+    /// Scratch file in the system temp directory, unique per test and process
+    fn temp_path(label: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("tournament_review-{label}-{}", std::process::id()))
+    }
+
+    #[test]
+    fn csv_writer_quotes_names() {
+        let path = temp_path("quoting.csv");
+        let mut csv_writer = create_csv_writer(&path, &args(&[])).unwrap();
+        csv_writer.write_record(["name", "kyoku_count"]).unwrap();
+        csv_writer.write_record(["a,\"b", "1"]).unwrap();
+        csv_writer.flush().unwrap();
+        drop(csv_writer);
+
+        let mut csv_reader = csv::Reader::from_path(&path).unwrap();
+        let records: Vec<csv::StringRecord> = csv_reader.records().map(|record| record.unwrap()).collect();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(&records[0][0], "a,\"b");
+        assert_eq!(&records[0][1], "1");
+    }
+
+    #[test]
+    fn skips_log_without_four_names() {
+        let mut json_value: serde_json::Value = serde_json::from_str(include_str!("../benches/data/sample_log.json")).unwrap();
+        json_value["name"] = serde_json::json!(["East", "South", "West"]);
+        let path = temp_path("three_names.json");
+        std::fs::write(&path, json_value.to_string()).unwrap();
+        let stats = process_log(&path, &args(&[]), &BTreeMap::new());
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(stats, Ok(None)));
+    }
+
+    #[test]
+    fn default_exclusion_and_opt_out() {
+        assert!(!name_included("ashlen_bot", &args(&[])));
+        assert!(name_included("ashley", &args(&[])));
+        assert!(name_included("ashlen_bot", &args(&["--exclude", ""])));
+        // a pattern of its own replaces the default
+        assert!(name_included("ashlen_bot", &args(&["--exclude", "bot2"])));
+        assert!(!name_included("bot2", &args(&["--exclude", "bot2"])));
+    }
+
+    #[test]
+    fn include_exact_over_exclude() {
+        let args = args(&["--exclude", "ash", "--include-exact", "ashley"]);
+        assert!(name_included("ashley", &args));
+        assert!(!name_included("ashley2", &args));
+        assert!(!name_included("ashlen", &args));
+        assert!(name_included("kasumi", &args));
+    }
+
+    #[test]
+    fn exclude_exact_over_include_exact() {
+        let args = args(&["--exclude-exact", "ashley", "--include-exact", "ashley"]);
+        assert!(!name_included("ashley", &args));
+    }
+
+    #[test]
+    fn exclude_exact_needs_the_whole_name() {
+        let args = args(&["--exclude", "", "--exclude-exact", "ash"]);
+        assert!(!name_included("ash", &args));
+        assert!(name_included("ashley", &args));
+        assert!(name_included("nash", &args));
+    }
+
+    #[test]
+    fn placement_percentile_skips_unplaced_players() {
+        let placed = |total_placement| PlayerInfo {
+            placed_game_count: 1,
+            total_placement,
+            ..Default::default()
+        };
+        let entries = [
+            ("first".to_owned(), placed(1.0)),
+            ("unplaced".to_owned(), PlayerInfo::default()),
+            ("last".to_owned(), placed(4.0)),
+        ];
+        let path = temp_path("percentile.csv");
+        write_info(&path, &entries, &PlayerLevels::new(), &args(&[])).unwrap();
+
+        let mut csv_reader = csv::Reader::from_path(&path).unwrap();
+        let records: Vec<csv::StringRecord> = csv_reader.records().map(|record| record.unwrap()).collect();
+        std::fs::remove_file(&path).unwrap();
+        let percentile_column = records[0].len() - 1;
+        let percentiles: Vec<&str> = records.iter().map(|record| &record[percentile_column]).collect();
+        assert_eq!(percentiles[1], "");
+        assert_ne!(percentiles[0], percentiles[2]);
+        assert!(percentiles[0].starts_with("100"));
+        assert!(percentiles[2].starts_with('0'));
+    }
+}
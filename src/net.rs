@@ -0,0 +1,143 @@
+use std::path::Path;
+
+use tracing::{info, warn};
+
+use crate::{Result, YilError};
+
+/// Tenhou serves the json of a log id here, the same data the viewer loads
+const TENHOU_LOG_JSON_URL: &str = "https://tenhou.net/5/mjlog2json.cgi?";
+
+/// Tenhou yaku names with the English names the Mahjong Soul logs of downloadlogs.js carry, which the yaku checks and
+/// the yaku.csv columns expect
+const YAKU_NAMES: &[(&str, &str)] = &[
+    ("門前清自摸和", "Fully Concealed Hand"),
+    ("立直", "Riichi"),
+    ("一発", "Ippatsu"),
+    ("槍槓", "Robbing a Kan"),
+    ("嶺上開花", "After a Kan"),
+    ("海底摸月", "Under the Sea"),
+    ("河底撈魚", "Under the River"),
+    ("平和", "Pinfu"),
+    ("断幺九", "All Simples"),
+    ("一盃口", "Pure Double Sequence"),
+    ("自風 東", "Seat Wind"),
+    ("自風 南", "Seat Wind"),
+    ("自風 西", "Seat Wind"),
+    ("自風 北", "Seat Wind"),
+    ("場風 東", "Prevalent Wind"),
+    ("場風 南", "Prevalent Wind"),
+    ("場風 西", "Prevalent Wind"),
+    ("場風 北", "Prevalent Wind"),
+    ("役牌 白", "White Dragon"),
+    ("役牌 發", "Green Dragon"),
+    ("役牌 中", "Red Dragon"),
+    ("両立直", "Double Riichi"),
+    ("七対子", "Seven Pairs"),
+    ("混全帯幺九", "Half Outside Hand"),
+    ("一気通貫", "Pure Straight"),
+    ("三色同順", "Mixed Triple Sequence"),
+    ("三色同刻", "Triple Triplets"),
+    ("三槓子", "Three Quads"),
+    ("対々和", "All Triplets"),
+    ("三暗刻", "Three Concealed Triplets"),
+    ("小三元", "Little Three Dragons"),
+    ("混老頭", "All Terminals and Honors"),
+    ("二盃口", "Twice Pure Double Sequence"),
+    ("純全帯幺九", "Fully Outside Hand"),
+    ("混一色", "Half Flush"),
+    ("清一色", "Full Flush"),
+    ("天和", "Blessing of Heaven"),
+    ("地和", "Blessing of Earth"),
+    ("大三元", "Big Three Dragons"),
+    ("四暗刻", "Four Concealed Triplets"),
+    ("四暗刻単騎", "Single-wait Four Concealed Triplets"),
+    ("字一色", "All Honors"),
+    ("緑一色", "All Green"),
+    ("清老頭", "All Terminals"),
+    ("九蓮宝燈", "Nine Gates"),
+    ("純正九蓮宝燈", "True Nine Gates"),
+    ("国士無双", "Thirteen Orphans"),
+    ("国士無双１３面", "Thirteen-wait Thirteen Orphans"),
+    ("大四喜", "Big Four Winds"),
+    ("小四喜", "Little Four Winds"),
+    ("四槓子", "Four Quads"),
+    ("ドラ", "Dora"),
+    ("裏ドラ", "Ura Dora"),
+    ("赤ドラ", "Red Five"),
+];
+
+/// Downloads every `tenhou.net/0/?log=...` link in `urls_path` that is not in `log_dir` yet. Tenhou names the yaku in
+/// Japanese, so they are renamed to English before the file is written and the downloaded logs are then processed like
+/// the converted ones. Other lines, such as Mahjong Soul links, are skipped.
+pub fn download_urls(urls_path: &Path, log_dir: &Path) -> Result<()> {
+    let urls = std::fs::read_to_string(urls_path).map_err(YilError::io(urls_path))?;
+    std::fs::create_dir_all(log_dir).map_err(YilError::io(log_dir))?;
+    let client = reqwest::blocking::Client::new();
+    for url in urls.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        let Some(log_id) = tenhou_log_id(url) else {
+            warn!(url, "skipping url that is not a tenhou log");
+            continue;
+        };
+        let log_path = log_dir.join(format!("{log_id}.json"));
+        if log_path.exists() {
+            continue;
+        }
+        info!(url, "downloading");
+        let download_error = |source: reqwest::Error| YilError::Download {
+            url: url.to_owned(),
+            source: source.into(),
+        };
+        let json_string = client
+            .get(format!("{TENHOU_LOG_JSON_URL}{log_id}"))
+            .send()
+            .and_then(|response| response.error_for_status())
+            .and_then(|response| response.text())
+            .map_err(download_error)?;
+        let mut json_value: serde_json::Value = serde_json::from_str(&json_string)?;
+        translate_yaku(&mut json_value);
+        std::fs::write(&log_path, serde_json::to_string(&json_value)?).map_err(YilError::io(&log_path))?;
+    }
+    Ok(())
+}
+
+/// Renames the yaku of every win through YAKU_NAMES, keeping their han such as `(1飜)`. Unknown names stay as they are
+fn translate_yaku(json_value: &mut serde_json::Value) {
+    let Some(kyokus) = json_value.get_mut("log").and_then(|log| log.as_array_mut()) else {
+        return;
+    };
+    // ["和了", deltas, [who, target, pao_who, point_string, yaku...], deltas, ...] with one detail per winner
+    let results = kyokus
+        .iter_mut()
+        .filter_map(|kyoku| kyoku.as_array_mut()?.last_mut()?.as_array_mut());
+    for result in results.filter(|result| result.first().and_then(|kind| kind.as_str()) == Some("和了")) {
+        let details = result
+            .iter_mut()
+            .skip(2)
+            .step_by(2)
+            .filter_map(|detail| detail.as_array_mut());
+        for yaku in details.flat_map(|detail| detail.iter_mut().skip(4)) {
+            let Some((name, han)) = yaku.as_str().and_then(|yaku| yaku.split_once('(')) else {
+                continue;
+            };
+            let Some((_, english)) = YAKU_NAMES.iter().find(|(japanese, _)| *japanese == name) else {
+                warn!(yaku = name, "keeping untranslated tenhou yaku");
+                continue;
+            };
+            let translated = format!("{english}({han}");
+            *yaku = translated.into();
+        }
+    }
+}
+
+fn tenhou_log_id(url: &str) -> Option<&str> {
+    if !url.contains("tenhou.net/") {
+        return None;
+    }
+    let (_, query) = url.split_once('?')?;
+    let log_id = query.split('&').find_map(|pair| pair.strip_prefix("log="))?;
+    // the id ends up in a file name
+    log_id
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-')
+        .then_some(log_id)
+}